@@ -1,5 +0,0 @@
-struct Binary<Expr> {
-    left: Box<Expr>,
-    operator: i64,
-    right: Box<Expr>,
-}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+// how many errors a phase reports unless told otherwise
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+// Keeps the first `max` of the errors a phase collected and sums up the
+// rest in a footer. A phase with errors stops the run, so capping each
+// phase also caps the errors reported across all of them.
+pub fn cap(mut errors: Vec<String>, max: usize) -> Vec<String> {
+    if errors.len() > max {
+        let more = errors.len() - max;
+        errors.truncate(max);
+        errors.push(format!(
+            "... and {} more {}",
+            more,
+            if more == 1 { "error" } else { "errors" }
+        ));
+    }
+
+    errors
+}
+
+// checks for code that is valid but likely a mistake
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Lint {
+    AssignInCondition, // `if (a = b)`, usually meant as `a == b`
+    EmptyBody,         // `while (c);`, a stray semicolon ending the loop
+    EmptyBlock,        // `{}`, off unless asked for
+    ShadowedNative,    // `var clock = 1;` hiding a native function
+    FloatEquality,     // `x == 0.1 + 0.2`, off unless asked for
+    DeadBranch,        // `if (false)`, a branch that never runs
+}
+
+impl Lint {
+    pub const ALL: &'static [Lint] = &[
+        Lint::AssignInCondition,
+        Lint::EmptyBody,
+        Lint::EmptyBlock,
+        Lint::ShadowedNative,
+        Lint::FloatEquality,
+        Lint::DeadBranch,
+    ];
+
+    // how the lint is named on the command line and in its reports
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::AssignInCondition => "assign-in-condition",
+            Lint::EmptyBody => "empty-body",
+            Lint::EmptyBlock => "empty-block",
+            Lint::ShadowedNative => "shadowed-native",
+            Lint::FloatEquality => "float-equality",
+            Lint::DeadBranch => "dead-branch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.iter().copied().find(|lint| lint.name() == name)
+    }
+
+    fn default_level(self) -> Level {
+        match self {
+            Lint::AssignInCondition | Lint::EmptyBody | Lint::ShadowedNative | Lint::DeadBranch => {
+                Level::Warn
+            }
+            Lint::EmptyBlock | Lint::FloatEquality => Level::Allow,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Allow, // not reported
+    Warn,  // reported, but the script still runs
+    Deny,  // reported as an error
+}
+
+// the level each lint is reported at
+#[derive(Clone, Default)]
+pub struct Lints {
+    levels: HashMap<Lint, Level>, // the lints not at their default level
+}
+
+impl Lints {
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels
+            .get(&lint)
+            .copied()
+            .unwrap_or_else(|| lint.default_level())
+    }
+
+    // the report for a lint at `line`, or None when it's allowed. Denied
+    // lints read as errors.
+    pub fn report(&self, lint: Lint, line: u32, message: &str) -> Option<(Level, String)> {
+        let kind = match self.level(lint) {
+            Level::Allow => return None,
+            Level::Warn => "Warning",
+            Level::Deny => "Error",
+        };
+
+        Some((
+            self.level(lint),
+            format!("[line {}] {}: {} [{}]", line, kind, message, lint.name()),
+        ))
+    }
+}
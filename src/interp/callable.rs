@@ -0,0 +1,200 @@
+use super::class::Instance;
+use super::environment::Environment;
+use super::{Flow, Interpreter};
+use crate::parse::ast;
+use crate::value::Value;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+// Anything that can be called from Lox: user functions, classes and native
+// functions. The interpreter checks the arity before calling. `call` takes
+// the Rc so a class can hand itself to the instances it creates.
+pub trait Callable: fmt::Display {
+    fn name(&self) -> Rc<str>;
+
+    // the most arguments it takes
+    fn arity(&self) -> usize;
+
+    // the fewest arguments it takes, when some have defaults
+    fn required(&self) -> usize {
+        self.arity()
+    }
+
+    fn call(self: Rc<Self>, interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value>;
+}
+
+pub struct Function {
+    declaration: Rc<ast::Function>,
+    closure: Rc<RefCell<Environment>>, // the scope the function was declared in
+    is_initializer: bool,              // an `init` method, which always returns `this`
+}
+
+impl Function {
+    pub fn new(
+        declaration: Rc<ast::Function>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        Function {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    // returns the method with `this` bound to the given instance
+    pub fn bind(&self, instance: Rc<RefCell<Instance>>) -> Function {
+        let mut environment = Environment::new(Some(self.closure.clone()));
+        environment.define("this".into(), Value::Instance(instance));
+
+        Function {
+            declaration: self.declaration.clone(),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+        }
+    }
+}
+
+impl Callable for Function {
+    fn name(&self) -> Rc<str> {
+        self.declaration.name.lexeme.clone()
+    }
+
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn required(&self) -> usize {
+        self.declaration.required()
+    }
+
+    // parameters left without an argument take their default, evaluated in
+    // the call's scope after the parameters before them are bound
+    fn call(self: Rc<Self>, interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value> {
+        let environment = Environment::new(Some(self.closure.clone()));
+        let environment = Rc::new(RefCell::new(environment));
+        let mut arguments = arguments.into_iter();
+
+        for (i, param) in self.declaration.params.iter().enumerate() {
+            let value = match arguments.next() {
+                Some(argument) => argument,
+                None => {
+                    let default = &self.declaration.defaults[i - self.declaration.required()];
+                    interpreter.evaluate_in(default, environment.clone())?
+                }
+            };
+
+            environment.borrow_mut().define(param.lexeme.clone(), value);
+        }
+
+        let flow = interpreter.execute_in(&self.declaration.body, environment)?;
+
+        // `init` always hands back the instance, even after a bare `return;`
+        if self.is_initializer {
+            return Ok(self
+                .closure
+                .borrow()
+                .find_at(0, "this")
+                .unwrap_or(Value::Nil));
+        }
+
+        match flow {
+            Flow::Return(value) => Ok(value),
+            // a `continue` never leaves the loop it is in
+            Flow::Normal | Flow::Continue => Ok(Value::Nil),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
+
+pub type NativeFn = fn(&Interpreter, &[Value]) -> Result<Value>;
+
+// A function implemented in Rust and predefined in the global scope.
+pub struct NativeFunction {
+    name: Rc<str>,
+    required: usize, // fewer than `arity` when trailing arguments are optional
+    arity: usize,
+    function: NativeFn,
+}
+
+impl NativeFunction {
+    pub fn new(name: Rc<str>, required: usize, arity: usize, function: NativeFn) -> Self {
+        NativeFunction {
+            name,
+            required,
+            arity,
+            function,
+        }
+    }
+}
+
+impl Callable for NativeFunction {
+    fn name(&self) -> Rc<str> {
+        self.name.clone()
+    }
+
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn required(&self) -> usize {
+        self.required
+    }
+
+    fn call(self: Rc<Self>, interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value> {
+        (self.function)(interpreter, &arguments)
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
+
+pub type NativeMethodFn = Box<dyn Fn(&[Value]) -> Result<Value>>;
+
+// A built-in method of a value that isn't an instance, such as a range's
+// `contains`. The value it was looked up on is captured in `function`.
+pub struct NativeMethod {
+    name: Rc<str>,
+    arity: usize,
+    function: NativeMethodFn,
+}
+
+impl NativeMethod {
+    pub fn new(name: Rc<str>, arity: usize, function: NativeMethodFn) -> Self {
+        NativeMethod {
+            name,
+            arity,
+            function,
+        }
+    }
+}
+
+impl Callable for NativeMethod {
+    fn name(&self) -> Rc<str> {
+        self.name.clone()
+    }
+
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(self: Rc<Self>, _: &mut Interpreter, arguments: Vec<Value>) -> Result<Value> {
+        (self.function)(&arguments)
+    }
+}
+
+impl fmt::Display for NativeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
@@ -0,0 +1,113 @@
+use super::callable::{Callable, Function};
+use super::{Interpreter, RuntimeError};
+use crate::scan::token::Token;
+use crate::value::Value;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+pub struct Class {
+    name: Rc<str>,
+    superclass: Option<Rc<Class>>,
+    methods: HashMap<Rc<str>, Rc<Function>>,
+}
+
+impl Class {
+    pub fn new(
+        name: Rc<str>,
+        superclass: Option<Rc<Class>>,
+        methods: HashMap<Rc<str>, Rc<Function>>,
+    ) -> Self {
+        Class {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    // methods not defined on the class itself are inherited
+    pub fn find_method(&self, name: &str) -> Option<Rc<Function>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+// Calling a class creates an instance and runs `init` on it, if there is one.
+impl Callable for Class {
+    fn name(&self) -> Rc<str> {
+        self.name.clone()
+    }
+
+    fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.arity())
+    }
+
+    fn required(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.required())
+    }
+
+    fn call(self: Rc<Self>, interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value> {
+        let instance = Rc::new(RefCell::new(Instance::new(self.clone())));
+
+        if let Some(initializer) = self.find_method("init") {
+            Rc::new(initializer.bind(instance.clone())).call(interpreter, arguments)?;
+        }
+
+        Ok(Value::Instance(instance))
+    }
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub struct Instance {
+    class: Rc<Class>,
+    fields: HashMap<Rc<str>, Value>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Instance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    // fields shadow methods; methods come back bound to the instance
+    pub fn get(instance: &Rc<RefCell<Instance>>, name: &Token) -> Result<Value> {
+        let this = instance.borrow();
+
+        if let Some(value) = this.fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match this.class.find_method(&name.lexeme) {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(instance.clone())))),
+            None => Err(RuntimeError::UndefinedProperty(
+                name.line,
+                name.lexeme.to_string(),
+            ))?,
+        }
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.fields.contains_key(name) || self.class.find_method(name).is_some()
+    }
+
+    pub fn set(&mut self, name: &Token, value: Value) {
+        self.fields.insert(name.lexeme.clone(), value);
+    }
+}
+
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
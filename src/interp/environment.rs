@@ -0,0 +1,106 @@
+use super::RuntimeError;
+use crate::scan::token::Token;
+use crate::value::Value;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// A scope's variables. Lookups and assignments that miss walk outwards
+// through the enclosing scopes up to the globals.
+#[derive(Default)]
+pub struct Environment {
+    values: HashMap<Rc<str>, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new(enclosing: Option<Rc<RefCell<Environment>>>) -> Self {
+        Environment {
+            values: HashMap::new(),
+            enclosing,
+        }
+    }
+
+    // redefining an existing name in the same scope simply overwrites it
+    pub fn define(&mut self, name: Rc<str>, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value> {
+        match self.find(&name.lexeme) {
+            Some(value) => Ok(value),
+            None => Err(RuntimeError::UndefinedVariable(
+                name.line,
+                name.lexeme.to_string(),
+            ))?,
+        }
+    }
+
+    // whether this scope itself defines `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    fn find(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.values.get(name) {
+            return Some(value.clone());
+        }
+
+        self.enclosing.as_ref()?.borrow().find(name)
+    }
+
+    // `distance` is how many scopes out the resolver found the variable, so
+    // a name declared later in a nearer scope can't shadow it
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value> {
+        match self.find_at(distance, &name.lexeme) {
+            Some(value) => Ok(value),
+            None => Err(RuntimeError::UndefinedVariable(
+                name.line,
+                name.lexeme.to_string(),
+            ))?,
+        }
+    }
+
+    pub fn find_at(&self, distance: usize, name: &str) -> Option<Value> {
+        if distance == 0 {
+            return self.values.get(name).cloned();
+        }
+
+        self.enclosing
+            .as_ref()?
+            .borrow()
+            .find_at(distance - 1, name)
+    }
+
+    pub fn assign_at(&mut self, distance: usize, name: &Token, value: Value) -> Result<()> {
+        if distance > 0 {
+            if let Some(enclosing) = &self.enclosing {
+                return enclosing.borrow_mut().assign_at(distance - 1, name, value);
+            }
+        } else if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        Err(RuntimeError::UndefinedVariable(
+            name.line,
+            name.lexeme.to_string(),
+        ))?
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<()> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(RuntimeError::UndefinedVariable(
+                name.line,
+                name.lexeme.to_string(),
+            ))?,
+        }
+    }
+}
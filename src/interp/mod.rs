@@ -0,0 +1,1703 @@
+pub mod callable;
+pub mod class;
+pub mod environment;
+pub mod native;
+
+use crate::parse::ast::{Depth, Expr, Stmt, StmtKind};
+use crate::parse::Parser;
+use crate::resolve::Resolver;
+use crate::scan::token::{Token, TokenType};
+use crate::scan::Scanner;
+use crate::value::{Range, Value};
+use anyhow::Result;
+use callable::{Callable, NativeFunction};
+use class::{Class, Instance};
+use environment::Environment;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+enum RuntimeError {
+    #[error("[line {0}] Operand must be a number.")]
+    OperandNotNumber(u32),
+
+    #[error("[line {0}] Operands must be numbers.")]
+    OperandsNotNumbers(u32),
+
+    #[error("[line {0}] Operands must be two numbers or two strings.")]
+    InvalidAddOperands(u32),
+
+    #[error("[line {0}] Undefined variable '{1}'.")]
+    UndefinedVariable(u32, String),
+
+    #[error("[line {0}] Can only call functions and classes.")]
+    NotCallable(u32),
+
+    #[error("[line {0}] Expected {1} arguments but got {2}.")]
+    ArityMismatch(u32, usize, usize),
+
+    #[error("[line {0}] Expected {1} to {2} arguments but got {3}.")]
+    ArityRangeMismatch(u32, usize, usize, usize),
+
+    #[error("[line {0}] Stack overflow in '{1}'.")]
+    StackOverflow(u32, String),
+
+    #[error("Stack overflow: the script is nested too deeply.")]
+    NestedTooDeeply,
+
+    #[error("[line {0}] Only instances have properties.")]
+    NotAnInstance(u32),
+
+    #[error("[line {0}] Only instances have fields.")]
+    NoFields(u32),
+
+    #[error("[line {0}] Undefined property '{1}'.")]
+    UndefinedProperty(u32, String),
+
+    #[error("[line {0}] Access to private member '{1}'.")]
+    PrivateMember(u32, String),
+
+    #[error("[line {0}] Superclass must be a class.")]
+    SuperclassNotClass(u32),
+
+    #[error("[line {0}] Range bounds must be integers.")]
+    RangeBoundsNotIntegers(u32),
+
+    #[error("[line {0}] Can only test membership in ranges, strings and instances.")]
+    NotAContainer(u32),
+
+    #[error("[line {0}] Can only iterate over ranges.")]
+    NotIterable(u32),
+
+    #[error("[line {0}] Arguments to '{1}' must be numbers.")]
+    ArgumentsNotNumbers(u32, String),
+
+    #[error("[line {0}] Can't repeat a string a negative number of times.")]
+    NegativeRepeat(u32),
+
+    #[error("[line {0}] Result is not a finite number.")]
+    NotFinite(u32),
+
+    #[error("[line {0}] Argument out of the domain of '{1}'.")]
+    OutOfDomain(u32, String),
+
+    // A value thrown by `throw`, printed, with the calls it was thrown from.
+    // Errors have to be Send, so the value itself waits in
+    // `Interpreter::thrown` for a `catch`.
+    #[error("[line {0}] Uncaught exception: {1}{2}")]
+    Thrown(u32, String, String),
+
+    #[error("[line {0}] Exit code must be an integer from 0 to 255.")]
+    InvalidExitCode(u32),
+
+    // `exit(code)`, unwinding to the top level through finally blocks
+    #[error("Exited with code {0}.")]
+    Exit(u8),
+
+    // `panic(message)`, which nothing catches
+    #[error("[line {0}] Panic: {1}")]
+    Panic(u32, String),
+}
+
+// Lox calls and nested statements and expressions all recurse through the
+// interpreter on the native stack, taking more or less of it depending on
+// the build and on what a function body looks like. Rather than budget a
+// fixed amount per call, the interpreter measures how much of the STACK_SIZE
+// thread stack it has used and reports a stack overflow while STACK_RESERVE
+// is still left for the frames between two checks.
+const MAX_FRAMES: usize = 10_000;
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+const STACK_RESERVE: usize = 1024 * 1024;
+
+// a function call in progress
+struct Frame {
+    function: Rc<str>,
+    line: u32, // of the call site
+}
+
+// how a statement finished. A `return` unwinds through the enclosing blocks
+// and loops up to the function call it belongs to, a `continue` up to the
+// innermost loop.
+pub enum Flow {
+    Normal,
+    Continue,
+    Return(Value),
+}
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>, // the innermost scope being executed
+    frames: Vec<Frame>,
+    stack_base: usize, // address of the native stack when the interpreter was created
+    trace: Option<String>, // every source run while tracing, statement spans index into it
+    strict_arithmetic: bool, // infinite and NaN results are runtime errors
+    catch_runtime_errors: bool, // `catch` gets other runtime errors' messages too
+    private_members: bool, // members named `_like_this` can only be used through `this`
+    thrown: Option<Value>, // the value of the `throw` being unwound
+    // the statements deferred in each block and function being executed,
+    // innermost last, with the scope each was deferred in
+    deferred: Vec<Vec<(Stmt, Rc<RefCell<Environment>>)>>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut globals = Environment::default();
+
+        for &(name, required, arity, function) in native::NATIVES {
+            let native = NativeFunction::new(name.into(), required, arity, function);
+            globals.define(name.into(), Value::Callable(Rc::new(native)));
+        }
+
+        let globals = Rc::new(RefCell::new(globals));
+
+        Interpreter {
+            globals: globals.clone(),
+            environment: globals,
+            frames: Vec::new(),
+            stack_base: stack_address(),
+            trace: None,
+            strict_arithmetic: false,
+            catch_runtime_errors: false,
+            private_members: false,
+            thrown: None,
+            deferred: Vec::new(),
+        }
+    }
+
+    pub fn with_catch_runtime_errors(mut self, catch_runtime_errors: bool) -> Self {
+        self.catch_runtime_errors = catch_runtime_errors;
+        self
+    }
+
+    pub fn with_private_members(mut self, private_members: bool) -> Self {
+        self.private_members = private_members;
+        self
+    }
+
+    pub fn with_strict_arithmetic(mut self, strict_arithmetic: bool) -> Self {
+        self.strict_arithmetic = strict_arithmetic;
+        self
+    }
+
+    // Turns on logging each statement to stderr as it executes. Every source
+    // run afterwards is added here, and it must be scanned with the returned
+    // offset so the statements' spans quote the right text; a function defined
+    // on an earlier REPL line still traces its own source.
+    pub fn trace(&mut self, source: &str) -> usize {
+        let traced = self.trace.get_or_insert_with(String::new);
+        let offset = traced.len();
+        traced.push_str(source);
+
+        offset
+    }
+
+    // statements deferred at the top level run once all of them have
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<()> {
+        self.deferred.push(Vec::new());
+
+        // the parser rejects `return` outside of functions
+        let result = self.execute_statements(statements);
+        self.run_deferred(result)?;
+
+        Ok(())
+    }
+
+    // Evaluates source holding a single expression, such as a debugger's
+    // watch expression, against the global scope. The CLI doesn't use it,
+    // it's for hosts embedding the interpreter.
+    #[allow(dead_code)]
+    pub fn eval_expr_string(&mut self, source: &str) -> Result<Value> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens()?;
+        let expr = Parser::new(tokens).parse_expression()?;
+        Resolver::new().resolve_expression(&expr)?;
+
+        self.evaluate(&expr)
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<Flow> {
+        self.check_stack()?;
+
+        if let Some(source) = &self.trace {
+            let text = source[stmt.span.clone()].lines().next().unwrap_or("");
+            eprintln!("> line {}: {}", stmt.line, text);
+        }
+
+        match &stmt.kind {
+            StmtKind::Block(statements) => {
+                let environment = Environment::new(Some(self.environment.clone()));
+                return self.execute_block(statements, environment);
+            }
+            StmtKind::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let superclass = match superclass {
+                    Some(superclass) => match self.evaluate(superclass)? {
+                        Value::Class(class) => Some(class),
+                        _ => Err(RuntimeError::SuperclassNotClass(name.line))?,
+                    },
+                    None => None,
+                };
+
+                // methods of a subclass close over a scope holding `super`
+                let mut closure = self.environment.clone();
+
+                if let Some(superclass) = &superclass {
+                    let mut environment = Environment::new(Some(closure));
+                    environment.define("super".into(), Value::Class(superclass.clone()));
+                    closure = Rc::new(RefCell::new(environment));
+                }
+
+                let methods: HashMap<_, _> = methods
+                    .iter()
+                    .map(|method| {
+                        let function = callable::Function::new(
+                            method.clone(),
+                            closure.clone(),
+                            &*method.name.lexeme == "init",
+                        );
+
+                        (method.name.lexeme.clone(), Rc::new(function))
+                    })
+                    .collect();
+
+                let class = Class::new(name.lexeme.clone(), superclass, methods);
+
+                self.environment
+                    .borrow_mut()
+                    .define(name.lexeme.clone(), Value::Class(Rc::new(class)));
+            }
+            StmtKind::Continue => return Ok(Flow::Continue),
+            StmtKind::Defer(body) => {
+                let deferred = (*body.clone(), self.environment.clone());
+
+                if let Some(scope) = self.deferred.last_mut() {
+                    scope.push(deferred);
+                }
+            }
+            StmtKind::Empty => {}
+            StmtKind::Expression(expr) => {
+                self.evaluate(expr)?;
+            }
+            StmtKind::ForIn {
+                name,
+                iterable,
+                body,
+            } => {
+                let Value::Range(range) = self.evaluate(iterable)? else {
+                    Err(RuntimeError::NotIterable(name.line))?
+                };
+
+                // each iteration gets its own variable, so closures capture
+                // the value it had then
+                for i in range.iter() {
+                    let mut environment = Environment::new(Some(self.environment.clone()));
+                    environment.define(name.lexeme.clone(), Value::Number(i as f64));
+
+                    let flow = self.execute_block(std::slice::from_ref(&**body), environment)?;
+
+                    if let Flow::Return(value) = flow {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+            }
+            StmtKind::Function(declaration) => {
+                let function =
+                    callable::Function::new(declaration.clone(), self.environment.clone(), false);
+
+                self.environment.borrow_mut().define(
+                    declaration.name.lexeme.clone(),
+                    Value::Callable(Rc::new(function)),
+                );
+            }
+            StmtKind::Global { name, value } => {
+                let value = self.evaluate(value)?;
+
+                self.globals.borrow_mut().define(name.lexeme.clone(), value);
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if self.evaluate(condition)?.is_truthy() {
+                    return self.execute(then_branch);
+                } else if let Some(else_branch) = else_branch {
+                    return self.execute(else_branch);
+                }
+            }
+            StmtKind::Print(expr) => println!("{}", self.evaluate(expr)?),
+            StmtKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.evaluate(value)?,
+                    None => Value::Nil,
+                };
+
+                return Ok(Flow::Return(value));
+            }
+            StmtKind::Throw { keyword, value } => {
+                let value = self.evaluate(value)?;
+                let error = RuntimeError::Thrown(keyword.line, value.to_string(), self.traceback());
+                self.thrown = Some(value);
+
+                Err(error)?
+            }
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                let environment = Environment::new(Some(self.environment.clone()));
+                let result = match (self.execute_block(body, environment), catch) {
+                    (Err(error), Some((name, catch))) => self.caught(error).and_then(|value| {
+                        let mut environment = Environment::new(Some(self.environment.clone()));
+                        environment.define(name.lexeme.clone(), value);
+
+                        self.execute_block(catch, environment)
+                    }),
+                    (result, _) => result,
+                };
+
+                // a panic is fatal, it doesn't wait for cleanup
+                if let (Some(finally), false) = (finally, panicked(&result)) {
+                    // a `try` in the finally block mustn't take the value of
+                    // a throw still unwinding
+                    let thrown = self.thrown.take();
+                    let environment = Environment::new(Some(self.environment.clone()));
+
+                    // a return or throw in the finally block replaces
+                    // whatever the try and catch blocks were doing
+                    if let flow @ (Flow::Continue | Flow::Return(_)) =
+                        self.execute_block(finally, environment)?
+                    {
+                        return Ok(flow);
+                    }
+
+                    self.thrown = thrown;
+                }
+
+                return result;
+            }
+            StmtKind::Var { name, initializer } => {
+                let value = match initializer {
+                    Some(initializer) => self.evaluate(initializer)?,
+                    None => Value::Nil,
+                };
+
+                self.environment
+                    .borrow_mut()
+                    .define(name.lexeme.clone(), value);
+            }
+            StmtKind::While {
+                condition,
+                body,
+                increment,
+            } => {
+                while self.evaluate(condition)?.is_truthy() {
+                    if let Flow::Return(value) = self.execute(body)? {
+                        return Ok(Flow::Return(value));
+                    }
+
+                    if let Some(increment) = increment {
+                        self.evaluate(increment)?;
+                    }
+                }
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    // runs statements in the given scope, restoring the current one afterwards
+    // even if a statement fails or returns
+    fn execute_block(&mut self, statements: &[Stmt], environment: Environment) -> Result<Flow> {
+        self.execute_in(statements, Rc::new(RefCell::new(environment)))
+    }
+
+    fn execute_in(
+        &mut self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Flow> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        self.deferred.push(Vec::new());
+
+        let result = self.execute_statements(statements);
+        let result = self.run_deferred(result);
+
+        self.environment = previous;
+
+        result
+    }
+
+    // Runs the statements deferred in the scope being left, the latest
+    // first, however the scope ended short of a panic. An error in one
+    // replaces the scope's result, and the rest still run.
+    fn run_deferred(&mut self, mut result: Result<Flow>) -> Result<Flow> {
+        let deferred = self.deferred.pop().unwrap_or_default();
+
+        if panicked(&result) {
+            return result;
+        }
+
+        for (statement, environment) in deferred.into_iter().rev() {
+            // like a finally block, a deferred `try` mustn't take the value
+            // of a throw still unwinding
+            let thrown = self.thrown.take();
+
+            match self.execute_in(std::slice::from_ref(&statement), environment) {
+                Err(error) => result = Err(error),
+                Ok(_) => self.thrown = thrown,
+            }
+        }
+
+        result
+    }
+
+    // evaluates an expression in the given scope, restoring the current one
+    // afterwards
+    fn evaluate_in(&mut self, expr: &Expr, environment: Rc<RefCell<Environment>>) -> Result<Value> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let result = self.evaluate(expr);
+
+        self.environment = previous;
+
+        result
+    }
+
+    fn execute_statements(&mut self, statements: &[Stmt]) -> Result<Flow> {
+        for statement in statements {
+            match self.execute(statement)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    // an infinite or NaN number is an error in strict arithmetic, rather
+    // than spreading through the calculations that follow
+    fn finite(&self, value: Value, line: u32) -> Result<Value> {
+        match value {
+            Value::Number(n) if self.strict_arithmetic && !n.is_finite() => {
+                Err(RuntimeError::NotFinite(line))?
+            }
+            value => Ok(value),
+        }
+    }
+
+    // The value a `catch` binds for an error, or the error back when it
+    // can't be caught. A stack overflow never is, there may be no stack left
+    // to handle it. Neither are `exit` and `panic`, which end the script.
+    fn caught(&mut self, error: anyhow::Error) -> Result<Value> {
+        match error.downcast_ref::<RuntimeError>() {
+            Some(RuntimeError::Thrown(..)) => Ok(self.thrown.take().unwrap_or(Value::Nil)),
+            Some(
+                RuntimeError::StackOverflow(..)
+                | RuntimeError::NestedTooDeeply
+                | RuntimeError::Exit(_)
+                | RuntimeError::Panic(..),
+            ) => Err(error),
+            Some(_) if self.catch_runtime_errors => Ok(Value::String(error.to_string())),
+            _ => Err(error),
+        }
+    }
+
+    // the calls in progress, innermost first
+    fn traceback(&self) -> String {
+        self.frames
+            .iter()
+            .rev()
+            .map(|frame| {
+                format!(
+                    "\n    in '{}' called at line {}",
+                    frame.function, frame.line
+                )
+            })
+            .collect()
+    }
+
+    // the line of the innermost call in progress, for natives' errors
+    fn call_line(&self) -> u32 {
+        self.frames.last().map_or(0, |frame| frame.line)
+    }
+
+    fn call(
+        &mut self,
+        function: Rc<dyn Callable>,
+        arguments: Vec<Value>,
+        line: u32,
+    ) -> Result<Value> {
+        if self.frames.len() >= MAX_FRAMES {
+            Err(self.stack_overflow())?;
+        }
+
+        self.frames.push(Frame {
+            function: function.name(),
+            line,
+        });
+
+        let result = function.call(self, arguments);
+
+        self.frames.pop();
+
+        result
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value> {
+        self.check_stack()?;
+
+        match expr {
+            Expr::Assign { name, value, depth } => {
+                let value = self.evaluate(value)?;
+
+                match depth.get() {
+                    Some(distance) => {
+                        self.environment
+                            .borrow_mut()
+                            .assign_at(distance, name, value.clone())?;
+                    }
+                    None => self.globals.borrow_mut().assign(name, value.clone())?,
+                }
+
+                Ok(value)
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+
+                match (operator.r#type, right) {
+                    (TokenType::In, Value::Instance(instance)) => {
+                        self.contains(&instance, left, operator)
+                    }
+                    (_, right) => self.finite(binary(operator, left, right)?, operator.line),
+                }
+            }
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => {
+                let callee = self.evaluate(callee)?;
+
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.evaluate(argument))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let function: Rc<dyn Callable> = match callee {
+                    Value::Callable(function) => function,
+                    Value::Class(class) => class,
+                    _ => Err(RuntimeError::NotCallable(paren.line))?,
+                };
+
+                check_arity(function.as_ref(), arguments.len(), paren.line)?;
+
+                self.call(function, arguments, paren.line)
+            }
+            Expr::Get { object, name } => match self.evaluate(object)? {
+                _ if self.is_private(object, name) => Err(RuntimeError::PrivateMember(
+                    name.line,
+                    name.lexeme.to_string(),
+                ))?,
+                Value::Instance(instance) => Instance::get(&instance, name),
+                Value::Range(range) => match native::range_property(range, &name.lexeme) {
+                    Some(property) => Ok(property),
+                    None => Err(RuntimeError::UndefinedProperty(
+                        name.line,
+                        name.lexeme.to_string(),
+                    ))?,
+                },
+                _ => Err(RuntimeError::NotAnInstance(name.line))?,
+            },
+            Expr::Grouping(expr) => self.evaluate(expr),
+            Expr::Literal(literal) => Ok(Value::from(literal)),
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.evaluate(left)?;
+
+                // the deciding operand is returned as is, not coerced to a bool
+                match (operator.r#type, left.is_truthy()) {
+                    (TokenType::Or, true) | (TokenType::And, false) => Ok(left),
+                    _ => self.evaluate(right),
+                }
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => {
+                let Value::Instance(instance) = self.evaluate(object)? else {
+                    Err(RuntimeError::NoFields(name.line))?
+                };
+
+                if self.is_private(object, name) {
+                    Err(RuntimeError::PrivateMember(
+                        name.line,
+                        name.lexeme.to_string(),
+                    ))?;
+                }
+
+                let value = self.evaluate(value)?;
+                instance.borrow_mut().set(name, value.clone());
+
+                Ok(value)
+            }
+            Expr::Super {
+                keyword,
+                method,
+                depth,
+            } => {
+                let superclass = self.look_up_variable(keyword, depth)?;
+
+                // the method's `this` is bound one scope in from `super`
+                let this = depth.get().and_then(|distance| {
+                    self.environment
+                        .borrow()
+                        .find_at(distance.checked_sub(1)?, "this")
+                });
+
+                let (Value::Class(superclass), Some(Value::Instance(instance))) =
+                    (superclass, this)
+                else {
+                    unreachable!("the resolver only allows 'super' in methods of subclasses")
+                };
+
+                match superclass.find_method(&method.lexeme) {
+                    Some(found) => Ok(Value::Callable(Rc::new(found.bind(instance)))),
+                    None => Err(RuntimeError::UndefinedProperty(
+                        method.line,
+                        method.lexeme.to_string(),
+                    ))?,
+                }
+            }
+            Expr::This { keyword, depth } => self.look_up_variable(keyword, depth),
+            Expr::Unary { operator, right } => {
+                let right = self.evaluate(right)?;
+
+                match (operator.r#type, right) {
+                    (TokenType::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
+                    (TokenType::Minus, _) => Err(RuntimeError::OperandNotNumber(operator.line))?,
+                    (_, right) => Ok(Value::Bool(!right.is_truthy())),
+                }
+            }
+            Expr::Variable {
+                name,
+                depth,
+                receiver,
+            } => {
+                if let (None, Some(receiver)) = (depth.get(), receiver.get()) {
+                    if let Some(member) = self.look_up_member(name, receiver) {
+                        return member;
+                    }
+                }
+
+                self.look_up_variable(name, depth)
+            }
+        }
+    }
+
+    // The stack grows down, so everything between the base and the current
+    // address is in use. The Interpreter isn't Send, so it always runs on the
+    // thread it was created on.
+    fn check_stack(&self) -> Result<()> {
+        let used = self.stack_base.saturating_sub(stack_address());
+
+        if used > STACK_SIZE - STACK_RESERVE {
+            Err(self.stack_overflow())?;
+        }
+
+        Ok(())
+    }
+
+    // blames the innermost call, if there is one
+    fn stack_overflow(&self) -> RuntimeError {
+        match self.frames.last() {
+            Some(frame) => RuntimeError::StackOverflow(frame.line, frame.function.to_string()),
+            None => RuntimeError::NestedTooDeeply,
+        }
+    }
+
+    // `item in instance` asks the instance's own `contains(item)`
+    fn contains(
+        &mut self,
+        instance: &Rc<RefCell<Instance>>,
+        item: Value,
+        operator: &Token,
+    ) -> Result<Value> {
+        let name = Token {
+            lexeme: "contains".into(),
+            ..operator.clone()
+        };
+
+        let Value::Callable(contains) = Instance::get(instance, &name)? else {
+            Err(RuntimeError::NotCallable(operator.line))?
+        };
+
+        check_arity(contains.as_ref(), 1, operator.line)?;
+        let found = self.call(contains, vec![item], operator.line)?;
+
+        Ok(Value::Bool(found.is_truthy()))
+    }
+
+    // A private member may only be used through `this`, which outside of a
+    // class's methods is a resolve error.
+    fn is_private(&self, object: &Expr, name: &Token) -> bool {
+        self.private_members && name.lexeme.starts_with('_') && !matches!(object, Expr::This { .. })
+    }
+
+    // Inside a method, a name that is neither a local nor a global is
+    // `this.name` when the instance has a field or method by that name.
+    fn look_up_member(&self, name: &Token, receiver: usize) -> Option<Result<Value>> {
+        if self.globals.borrow().contains(&name.lexeme) {
+            return None;
+        }
+
+        let Some(Value::Instance(instance)) = self.environment.borrow().find_at(receiver, "this")
+        else {
+            return None;
+        };
+
+        if !instance.borrow().has(&name.lexeme) {
+            return None;
+        }
+
+        Some(Instance::get(&instance, name))
+    }
+
+    // locals are found at the depth the resolver worked out, anything
+    // unresolved is a global
+    fn look_up_variable(&self, name: &Token, depth: &Depth) -> Result<Value> {
+        match depth.get() {
+            Some(distance) => self.environment.borrow().get_at(distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+}
+
+// How a runtime error ends the process: its exit code, and whether it still
+// needs reporting. Runtime errors exit with 70, as in sysexits.h, and
+// `exit(code)` with its code. Other errors aren't runtime errors and exit
+// with 1.
+pub fn exit_status(error: &anyhow::Error) -> Option<(i32, bool)> {
+    match error.downcast_ref::<RuntimeError>()? {
+        RuntimeError::Exit(code) => Some((*code as i32, false)),
+        _ => Some((70, true)),
+    }
+}
+
+// whether an error ends a REPL session too, rather than only its line
+pub fn is_fatal(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<RuntimeError>(),
+        Some(RuntimeError::Exit(_) | RuntimeError::Panic(..))
+    )
+}
+
+pub fn binary(operator: &Token, left: Value, right: Value) -> Result<Value> {
+    let value = match (operator.r#type, left, right) {
+        (TokenType::EqualEqual, l, r) => Value::Bool(l == r),
+        (TokenType::BangEqual, l, r) => Value::Bool(l != r),
+        (TokenType::Plus, Value::Number(l), Value::Number(r)) => Value::Number(l + r),
+        (TokenType::Plus, Value::String(l), Value::String(r)) => Value::String(l + &r),
+        (TokenType::Plus, _, _) => Err(RuntimeError::InvalidAddOperands(operator.line))?,
+        (TokenType::Minus, Value::Number(l), Value::Number(r)) => Value::Number(l - r),
+        (TokenType::Star, Value::Number(l), Value::Number(r)) => Value::Number(l * r),
+        // the count is truncated, `"ab" * 2.9` is "abab"
+        (TokenType::Star, Value::String(l), Value::Number(r)) => {
+            if r < 0.0 {
+                Err(RuntimeError::NegativeRepeat(operator.line))?
+            }
+
+            Value::String(l.repeat(r as usize))
+        }
+        (TokenType::Slash, Value::Number(l), Value::Number(r)) => Value::Number(l / r),
+        (TokenType::StarStar, Value::Number(l), Value::Number(r)) => Value::Number(l.powf(r)),
+        (TokenType::DotDot | TokenType::DotDotEqual, l, r) => {
+            let (Some(start), Some(end)) = (integer(&l), integer(&r)) else {
+                Err(RuntimeError::RangeBoundsNotIntegers(operator.line))?
+            };
+
+            Value::Range(Range {
+                start,
+                end,
+                inclusive: operator.r#type == TokenType::DotDotEqual,
+            })
+        }
+        (TokenType::In, l, Value::Range(r)) => {
+            Value::Bool(matches!(l, Value::Number(n) if r.contains(n)))
+        }
+        (TokenType::In, Value::String(l), Value::String(r)) => Value::Bool(r.contains(&*l)),
+        (TokenType::In, _, _) => Err(RuntimeError::NotAContainer(operator.line))?,
+        (TokenType::Greater, Value::Number(l), Value::Number(r)) => Value::Bool(l > r),
+        (TokenType::GreaterEqual, Value::Number(l), Value::Number(r)) => Value::Bool(l >= r),
+        (TokenType::Less, Value::Number(l), Value::Number(r)) => Value::Bool(l < r),
+        (TokenType::LessEqual, Value::Number(l), Value::Number(r)) => Value::Bool(l <= r),
+        _ => Err(RuntimeError::OperandsNotNumbers(operator.line))?,
+    };
+
+    Ok(value)
+}
+
+fn check_arity(function: &dyn Callable, arguments: usize, line: u32) -> Result<()> {
+    let (min, max) = (function.required(), function.arity());
+
+    if min == max && arguments != max {
+        Err(RuntimeError::ArityMismatch(line, max, arguments))?;
+    } else if arguments < min || arguments > max {
+        Err(RuntimeError::ArityRangeMismatch(line, min, max, arguments))?;
+    }
+
+    Ok(())
+}
+
+fn panicked(result: &Result<Flow>) -> bool {
+    matches!(result, Err(error)
+        if matches!(error.downcast_ref(), Some(RuntimeError::Panic(..))))
+}
+
+// the address of a local in a frame of its own, close to the top of the
+// native stack
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+fn integer(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) if n.fract() == 0.0 => Some(*n as i64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // runs every statement but the last, which must be an expression, and
+    // returns what that expression evaluates to
+    fn eval(source: &str) -> Result<String> {
+        eval_with(Interpreter::new(), source)
+    }
+
+    fn eval_with(mut interpreter: Interpreter, source: &str) -> Result<String> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens()?;
+        let mut statements = Parser::new(tokens).parse()?;
+        Resolver::new().resolve(&statements)?;
+
+        let Some(StmtKind::Expression(last)) = statements.pop().map(|stmt| stmt.kind) else {
+            panic!("the source must end with an expression statement");
+        };
+
+        interpreter.interpret(&statements)?;
+
+        Ok(interpreter.evaluate(&last)?.to_string())
+    }
+
+    #[test]
+    fn evaluates_an_expression_string() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            interpreter.eval_expr_string("1+1").unwrap().to_string(),
+            "2"
+        );
+    }
+
+    #[test]
+    fn evaluates_an_expression_string_against_the_globals() {
+        let tokens = Scanner::new("var answer = 41;".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(&statements).unwrap();
+
+        let value = interpreter.eval_expr_string("answer + 1").unwrap();
+        assert_eq!(value.to_string(), "42");
+    }
+
+    #[test]
+    fn an_expression_string_must_be_a_single_expression() {
+        let mut interpreter = Interpreter::new();
+
+        let statement = interpreter.eval_expr_string("var a = 1;").err().unwrap();
+        assert_eq!(
+            statement.to_string(),
+            "[line 1] Error at 'var': Expect expression."
+        );
+
+        let two = interpreter.eval_expr_string("1; 2").err().unwrap();
+        assert_eq!(
+            two.to_string(),
+            "[line 1] Error at ';': Expect end of expression."
+        );
+    }
+
+    #[test]
+    fn closures_see_the_variables_in_scope_where_they_were_declared() {
+        let source = r#"
+            var a = "global";
+            var seen = "";
+            {
+                fun showA() { seen = seen + a; }
+                showA();
+                var a = "block";
+                showA();
+            }
+            seen;
+        "#;
+
+        assert_eq!(eval(source).unwrap(), "globalglobal");
+    }
+
+    #[test]
+    fn super_calls_the_superclass_method_on_the_same_instance() {
+        let source = "
+            class A { name() { return this.n + \"A\"; } }
+            class B < A { init() { this.n = \"b\"; } name() { return super.name() + \"B\"; } }
+            class C < B { name() { return super.name() + \"C\"; } }
+            C().name();
+        ";
+
+        assert_eq!(eval(source).unwrap(), "bABC");
+    }
+
+    // runs on a thread with the stack the interpreter gets in main
+    fn eval_on_big_stack(source: String) -> Result<String> {
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || eval(&source))
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    #[test]
+    fn the_stack_is_deep_enough_for_the_frame_limit() {
+        let recurse = |depth: usize| {
+            eval_on_big_stack(format!(
+                "fun f(n) {{ if (n == 0) return 0; return 1 + f(n - 1); }} f({});",
+                depth
+            ))
+        };
+
+        assert_eq!(
+            recurse(MAX_FRAMES - 1).unwrap(),
+            (MAX_FRAMES - 1).to_string()
+        );
+        assert_eq!(
+            recurse(MAX_FRAMES).unwrap_err().to_string(),
+            "[line 1] Stack overflow in 'f'."
+        );
+    }
+
+    // how much native stack a call takes depends on the build, so these
+    // only ask for an error instead of an aborted process
+    #[test]
+    fn deeply_nested_recursion_overflows_with_an_error() {
+        let overflow = "[line 1] Stack overflow in 'f'.";
+
+        let blocks = eval_on_big_stack(format!(
+            "fun f(n) {{ {} if (n == 0) return 0; return 1 + f(n - 1); {} }} f(9990);",
+            "{".repeat(200),
+            "}".repeat(200)
+        ));
+        assert_eq!(blocks.unwrap_err().to_string(), overflow);
+
+        let parens = eval_on_big_stack(format!(
+            "fun f(n) {{ if (n == 0) return 0; return {}1 + f(n - 1){}; }} f(9990);",
+            "(".repeat(40),
+            ")".repeat(40)
+        ));
+        match parens {
+            Ok(result) => assert_eq!(result, "9990"),
+            Err(e) => assert_eq!(e.to_string(), overflow),
+        }
+    }
+
+    #[test]
+    fn methods_returning_this_chain() {
+        let source = "
+            class Builder {
+                init() { this.digits = 0; }
+                set(digit) { this.digits = this.digits * 10 + digit; return this; }
+                build() { return this.digits; }
+            }
+            var b = Builder();
+            b.set(1).set(2).build();
+        ";
+
+        assert_eq!(eval(source).unwrap(), "12");
+    }
+
+    #[test]
+    fn continue_in_a_for_loop_still_runs_the_increment() {
+        let source = "
+            var sum = 0;
+            for (var i = 0; i < 10; i = i + 1) {
+                if (i == 3 or i == 5) continue;
+                sum = sum + i;
+            }
+            sum;
+        ";
+
+        assert_eq!(eval(source).unwrap(), (45 - 3 - 5).to_string());
+    }
+
+    #[test]
+    fn continue_skips_the_rest_of_while_and_for_in_bodies() {
+        let source = "
+            var seen = \"\";
+            var i = 0;
+            while (i < 4) { i = i + 1; if (i == 2) continue; seen = seen + \"w\"; }
+            for (j in 0..4) { if (j == 2) { continue; } seen = seen + \"f\"; }
+            seen;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "wwwfff");
+    }
+
+    #[test]
+    fn continue_outside_of_a_loop_is_an_error() {
+        let error = eval("while (false) { fun f() { continue; } } nil;").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at 'continue': Can't use 'continue' outside of a loop."
+        );
+    }
+
+    #[test]
+    fn omitted_arguments_take_their_defaults() {
+        let greet = "fun greet(name, greeting = \"hi\", end = greeting + \"!\") {
+            return greeting + \" \" + name + end;
+        }";
+        let call = |arguments: &str| eval(&format!("{} greet({});", greet, arguments));
+
+        assert_eq!(call("\"bob\"").unwrap(), "hi bobhi!");
+        assert_eq!(call("\"bob\", \"yo\"").unwrap(), "yo bobyo!");
+        assert_eq!(call("\"bob\", \"yo\", \"?\"").unwrap(), "yo bob?");
+    }
+
+    #[test]
+    fn parameters_without_defaults_are_still_required() {
+        let greet = "fun greet(name, greeting = \"hi\") { return greeting + name; }";
+        let call = |arguments: &str| eval(&format!("{} var f = greet; f({});", greet, arguments));
+
+        assert_eq!(
+            call("").unwrap_err().to_string(),
+            "[line 1] Expected 1 to 2 arguments but got 0."
+        );
+        assert_eq!(
+            call("1, 2, 3").unwrap_err().to_string(),
+            "[line 1] Expected 1 to 2 arguments but got 3."
+        );
+
+        let error = eval("fun f(a = 1, b) {} nil;").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at 'b': Expect a default value after a defaulted parameter."
+        );
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        assert_eq!(eval("2 ** 3 ** 2;").unwrap(), "512");
+        assert_eq!(eval("(2 ** 3) ** 2;").unwrap(), "64");
+    }
+
+    #[test]
+    fn power_binds_tighter_than_unary_minus() {
+        assert_eq!(eval("-2 ** 2;").unwrap(), "-4");
+        assert_eq!(eval("2 ** -1;").unwrap(), "0.5");
+    }
+
+    #[test]
+    fn power_of_a_non_number_is_an_error() {
+        let error = eval("\"2\" ** 3;").unwrap_err();
+
+        assert_eq!(error.to_string(), "[line 1] Operands must be numbers.");
+    }
+
+    #[test]
+    fn iterates_an_exclusive_range() {
+        let source =
+            "var n = 0; var sum = 0; for (i in 0..10) { n = n + 1; sum = sum + i; } n + sum;";
+
+        assert_eq!(eval(source).unwrap(), (10 + 45).to_string());
+    }
+
+    #[test]
+    fn iterates_an_inclusive_range() {
+        let source =
+            "var n = 0; var sum = 0; for (i in 0..=10) { n = n + 1; sum = sum + i; } n + sum;";
+
+        assert_eq!(eval(source).unwrap(), (11 + 55).to_string());
+    }
+
+    #[test]
+    fn an_empty_range_runs_no_iterations() {
+        assert_eq!(
+            eval("var n = 0; for (i in 3..3) n = n + 1; n;").unwrap(),
+            "0"
+        );
+        assert_eq!(
+            eval("var n = 0; for (i in 3..=2) n = n + 1; n;").unwrap(),
+            "0"
+        );
+    }
+
+    #[test]
+    fn ranges_are_values_with_a_length() {
+        assert_eq!(eval("var r = 0..10; r.length;").unwrap(), "10");
+        assert_eq!(eval("(0..=10).length;").unwrap(), "11");
+        assert_eq!(eval("(5..2).length;").unwrap(), "0");
+    }
+
+    #[test]
+    fn ranges_know_their_members() {
+        let contains = |range: &str, x: &str| eval(&format!("({}).contains({});", range, x));
+
+        assert_eq!(contains("0..10", "0").unwrap(), "true");
+        assert_eq!(contains("0..10", "10").unwrap(), "false");
+        assert_eq!(contains("0..=10", "10").unwrap(), "true");
+        assert_eq!(contains("0..10", "2.5").unwrap(), "false");
+        assert_eq!(contains("0..10", "\"1\"").unwrap(), "false");
+    }
+
+    #[test]
+    fn range_bounds_must_be_integers() {
+        let error = eval("0..1.5;").unwrap_err();
+
+        assert_eq!(error.to_string(), "[line 1] Range bounds must be integers.");
+    }
+
+    #[test]
+    fn closures_keep_their_own_variables_alive() {
+        let source = "
+            fun counter() { var i = 0; fun inc() { i = i + 1; return i; } return inc; }
+            var c = counter();
+            c();
+            c();
+        ";
+
+        assert_eq!(eval(source).unwrap(), "2");
+    }
+
+    #[test]
+    fn approx_eq_compares_within_epsilon() {
+        assert_eq!(eval("0.1 + 0.2 == 0.3;").unwrap(), "false");
+        assert_eq!(
+            eval("approx_eq(0.1 + 0.2, 0.3, 0.000001);").unwrap(),
+            "true"
+        );
+        assert_eq!(eval("approx_eq(1, 1.1, 0.01);").unwrap(), "false");
+    }
+
+    #[test]
+    fn approx_eq_takes_numbers() {
+        let error = eval("approx_eq(1, \"1\", 0.1);").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Arguments to 'approx_eq' must be numbers."
+        );
+    }
+
+    #[test]
+    fn methods_call_other_methods_without_this() {
+        let source = "
+            class Greeter {
+                name() { return \"lox\"; }
+                greet() { return \"hi \" + name(); }
+            }
+            Greeter().greet();
+        ";
+
+        assert_eq!(eval(source).unwrap(), "hi lox");
+    }
+
+    #[test]
+    fn methods_read_fields_without_this() {
+        let source = "
+            class Counter {
+                init() { this.count = 2; }
+                doubled() { return count * 2; }
+            }
+            Counter().doubled();
+        ";
+
+        assert_eq!(eval(source).unwrap(), "4");
+    }
+
+    #[test]
+    fn locals_and_globals_win_over_members() {
+        let source = "
+            var count = 10;
+            class Counter {
+                init() { this.count = 2; this.step = 1; }
+                outer() { return count; }
+                local() { var step = 5; return step; }
+            }
+            var c = Counter();
+            c.outer() + c.local();
+        ";
+
+        assert_eq!(eval(source).unwrap(), "15");
+    }
+
+    #[test]
+    fn unknown_names_in_methods_are_still_undefined() {
+        let source = "class A { f() { return missing; } } A().f();";
+
+        assert_eq!(
+            eval(source).unwrap_err().to_string(),
+            "[line 1] Undefined variable 'missing'."
+        );
+    }
+
+    #[test]
+    fn strings_repeat() {
+        assert_eq!(eval("\"ab\" * 3;").unwrap(), "ababab");
+        assert_eq!(eval("\"ab\" * 2.9;").unwrap(), "abab");
+        assert_eq!(eval("\"ab\" * 0;").unwrap(), "");
+    }
+
+    #[test]
+    fn strings_dont_repeat_a_negative_number_of_times() {
+        assert_eq!(
+            eval("\"ab\" * -1;").unwrap_err().to_string(),
+            "[line 1] Can't repeat a string a negative number of times."
+        );
+        assert_eq!(
+            eval("3 * \"ab\";").unwrap_err().to_string(),
+            "[line 1] Operands must be numbers."
+        );
+    }
+
+    #[test]
+    fn arithmetic_can_overflow_to_infinity_and_nan() {
+        assert_eq!(eval("10 ** 308 * 10;").unwrap(), "inf");
+        assert_eq!(eval("0 / 0;").unwrap(), "NaN");
+    }
+
+    #[test]
+    fn strict_arithmetic_rejects_infinity_and_nan() {
+        let strict = || Interpreter::new().with_strict_arithmetic(true);
+
+        assert_eq!(
+            eval_with(strict(), "10 ** 308 * 10;")
+                .unwrap_err()
+                .to_string(),
+            "[line 1] Result is not a finite number."
+        );
+        assert_eq!(
+            eval_with(strict(), "0 / 0;").unwrap_err().to_string(),
+            "[line 1] Result is not a finite number."
+        );
+        assert_eq!(eval_with(strict(), "1 / 4;").unwrap(), "0.25");
+    }
+
+    #[test]
+    fn math_natives_compute_known_values() {
+        let number = |source: &str| eval(source).unwrap().parse::<f64>().unwrap();
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+
+        assert!(close(number("hypot(3, 4);"), 5.0));
+        assert!(close(number("sin(0);"), 0.0));
+        assert!(close(number("cos(0);"), 1.0));
+        assert!(close(number("tan(1);"), 1.0f64.tan()));
+        assert!(close(number("atan2(1, 1);"), std::f64::consts::FRAC_PI_4));
+        assert!(close(number("log(exp(2));"), 2.0));
+        assert!(close(number("log(8, 2);"), 3.0));
+        assert!(close(number("exp(0);"), 1.0));
+    }
+
+    #[test]
+    fn math_domain_errors_are_nan_unless_strict() {
+        assert_eq!(eval("log(-1);").unwrap(), "NaN");
+
+        let strict = Interpreter::new().with_strict_arithmetic(true);
+        assert_eq!(
+            eval_with(strict, "log(-1);").unwrap_err().to_string(),
+            "[line 1] Argument out of the domain of 'log'."
+        );
+    }
+
+    #[test]
+    fn log_takes_one_or_two_arguments() {
+        assert_eq!(
+            eval("log();").unwrap_err().to_string(),
+            "[line 1] Expected 1 to 2 arguments but got 0."
+        );
+    }
+
+    #[test]
+    fn catch_binds_the_thrown_value() {
+        let source = "
+            fun check(n) { if (n < 0) throw n * 2; return n; }
+            var caught;
+            try { check(1); check(-2); caught = \"not thrown\"; } catch (e) { caught = e; }
+            caught;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "-4");
+    }
+
+    #[test]
+    fn catch_can_throw_again() {
+        let source = "
+            var seen = \"\";
+            try {
+                try { throw \"inner\"; } catch (e) { seen = seen + e; throw e + \"!\"; }
+            } catch (e) {
+                seen = seen + \" \" + e;
+            }
+            seen;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "inner inner!");
+    }
+
+    #[test]
+    fn uncaught_throws_report_where_they_came_from() {
+        let source = "
+            fun inner() { throw \"oops\"; }
+            fun outer() { inner(); }
+            outer();
+        ";
+
+        assert_eq!(
+            eval(source).unwrap_err().to_string(),
+            "[line 2] Uncaught exception: oops\n    in 'inner' called at line 3\n    in 'outer' called at line 4"
+        );
+    }
+
+    #[test]
+    fn runtime_errors_are_only_caught_when_asked_for() {
+        let source = "var caught; try { 1 + nil; } catch (e) { caught = e; } caught;";
+
+        assert_eq!(
+            eval(source).unwrap_err().to_string(),
+            "[line 1] Operands must be two numbers or two strings."
+        );
+
+        let catching = Interpreter::new().with_catch_runtime_errors(true);
+        assert_eq!(
+            eval_with(catching, source).unwrap(),
+            "[line 1] Operands must be two numbers or two strings."
+        );
+    }
+
+    #[test]
+    fn finally_runs_after_a_normal_try() {
+        let source = "var seen = \"\"; try { seen = \"try\"; } finally { seen = seen + \" finally\"; } seen;";
+
+        assert_eq!(eval(source).unwrap(), "try finally");
+    }
+
+    #[test]
+    fn finally_runs_after_a_caught_throw() {
+        let source = "
+            var seen = \"\";
+            try { throw \"x\"; } catch (e) { seen = \"caught \" + e; } finally { seen = seen + \" finally\"; }
+            seen;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "caught x finally");
+    }
+
+    #[test]
+    fn finally_runs_before_an_uncaught_throw_leaves() {
+        let source = "
+            var seen = \"\";
+            fun f() { try { throw \"x\"; } finally { seen = \"finally\"; } }
+            try { f(); } catch (e) { seen = seen + \" then caught \" + e; }
+            seen;
+        ";
+        assert_eq!(eval(source).unwrap(), "finally then caught x");
+
+        let uncaught = "try { throw \"x\"; } finally { var done = true; } 1;";
+        assert_eq!(
+            eval(uncaught).unwrap_err().to_string(),
+            "[line 1] Uncaught exception: x"
+        );
+    }
+
+    #[test]
+    fn finally_runs_when_the_try_returns() {
+        let source = "
+            var seen = \"\";
+            fun f() { try { return \"try\"; } finally { seen = \"finally\"; } }
+            f() + \" \" + seen;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "try finally");
+    }
+
+    #[test]
+    fn return_in_finally_overrides() {
+        let returned = "fun f() { try { return 1; } finally { return 2; } } f();";
+        assert_eq!(eval(returned).unwrap(), "2");
+
+        let thrown = "fun f() { try { throw 1; } finally { return 2; } } f();";
+        assert_eq!(eval(thrown).unwrap(), "2");
+    }
+
+    #[test]
+    fn throw_in_finally_overrides() {
+        let source = "
+            var caught;
+            fun f() { try { return 1; } finally { throw 2; } }
+            try { f(); } catch (e) { caught = e; }
+            caught;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "2");
+    }
+
+    #[test]
+    fn a_try_in_finally_keeps_the_pending_throw() {
+        let source = "
+            var caught;
+            try {
+                try { throw \"outer\"; } finally { try { throw \"inner\"; } catch (e) {} }
+            } catch (e) {
+                caught = e;
+            }
+            caught;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "outer");
+    }
+
+    #[test]
+    fn try_needs_catch_or_finally() {
+        assert_eq!(
+            eval("try {} 1;").unwrap_err().to_string(),
+            "[line 1] Error at '1': Expect 'catch' or 'finally' after try block."
+        );
+    }
+
+    #[test]
+    fn exit_codes_are_bytes() {
+        for code in ["-1", "256", "1.5", "\"1\""] {
+            assert_eq!(
+                eval(&format!("exit({});", code)).unwrap_err().to_string(),
+                "[line 1] Exit code must be an integer from 0 to 255."
+            );
+        }
+    }
+
+    #[test]
+    fn exit_and_panic_cannot_be_caught() {
+        let catching = || Interpreter::new().with_catch_runtime_errors(true);
+
+        assert_eq!(
+            eval_with(catching(), "try { exit(3); } catch (e) {} 1;")
+                .unwrap_err()
+                .to_string(),
+            "Exited with code 3."
+        );
+        assert_eq!(
+            eval_with(catching(), "try { panic(\"boom\"); } catch (e) {} 1;")
+                .unwrap_err()
+                .to_string(),
+            "[line 1] Panic: boom"
+        );
+    }
+
+    #[test]
+    fn deferred_statements_run_in_reverse_order() {
+        let source = "
+            var seen = \"\";
+            fun note(s) { seen = seen + s; }
+            fun f() { defer note(\"1\"); defer { note(\"2\"); } note(\"body \"); }
+            f();
+            seen;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "body 21");
+    }
+
+    #[test]
+    fn deferred_statements_run_when_their_block_exits() {
+        let source = "
+            var seen = \"\";
+            for (var i = 0; i < 2; i = i + 1) { defer seen = seen + \"d \"; seen = seen + \"b\"; }
+            seen;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "bd bd ");
+    }
+
+    #[test]
+    fn deferred_statements_run_on_an_early_return() {
+        let source = "
+            var seen = \"\";
+            fun f(n) { defer seen = seen + \"deferred\"; if (n > 0) return \"early\"; seen = \"not reached\"; }
+            f(1) + \" \" + seen;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "early deferred");
+    }
+
+    #[test]
+    fn deferred_statements_run_on_a_throw() {
+        let source = "
+            var seen = \"\";
+            fun f() { defer seen = seen + \"deferred\"; throw \"x\"; }
+            try { f(); } catch (e) { seen = seen + \" then caught \" + e; }
+            seen;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "deferred then caught x");
+    }
+
+    #[test]
+    fn deferred_statements_see_their_scope() {
+        let source = "
+            var seen;
+            fun f() { var x = 1; defer seen = x; x = 2; }
+            f();
+            seen;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "2");
+    }
+
+    #[test]
+    fn deferred_statements_cannot_return() {
+        assert_eq!(
+            eval("fun f() { defer return 1; } 1;")
+                .unwrap_err()
+                .to_string(),
+            "[line 1] Error at 'return': Can't return from a deferred statement."
+        );
+    }
+
+    #[test]
+    fn global_assigns_the_global_binding() {
+        let source = "
+            var counter = 0;
+            fun bump() { global counter = counter + 1; }
+            bump();
+            bump();
+            counter;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "2");
+    }
+
+    #[test]
+    fn var_in_a_function_shadows_the_global() {
+        let source = "
+            var counter = 0;
+            fun shadow() { var counter = 5; counter = counter + 1; }
+            fun both() { var counter = 5; global counter = counter + 1; }
+            shadow();
+            var shadowed = counter;
+            both();
+            shadowed + counter;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "6");
+    }
+
+    #[test]
+    fn global_defines_a_missing_global() {
+        let source = "
+            fun f() { global fresh = 1; }
+            f();
+            fresh;
+        ";
+
+        assert_eq!(eval(source).unwrap(), "1");
+    }
+
+    #[test]
+    fn private_members_are_usable_inside_the_class() {
+        let private = Interpreter::new().with_private_members(true);
+        let source = "
+            class Account {
+                init() { this._balance = 0; }
+                _add(amount) { this._balance = this._balance + amount; }
+                deposit(amount) { this._add(amount); return this._balance; }
+            }
+            Account().deposit(5);
+        ";
+
+        assert_eq!(eval_with(private, source).unwrap(), "5");
+    }
+
+    #[test]
+    fn private_members_are_denied_outside_the_class() {
+        let private = || Interpreter::new().with_private_members(true);
+        let source = "
+            class Account { init() { this._balance = 0; } }
+            var account = Account();
+        ";
+
+        assert_eq!(
+            eval_with(private(), &format!("{source} account._balance;"))
+                .unwrap_err()
+                .to_string(),
+            "[line 4] Access to private member '_balance'."
+        );
+        assert_eq!(
+            eval_with(private(), &format!("{source} account._balance = 1;"))
+                .unwrap_err()
+                .to_string(),
+            "[line 4] Access to private member '_balance'."
+        );
+        assert_eq!(eval(&format!("{source} account._balance;")).unwrap(), "0");
+    }
+
+    #[test]
+    fn in_asks_an_instance_for_contains() {
+        let source = "
+            class Set {
+                init(a, b, c) { this.a = a; this.b = b; this.c = c; }
+                contains(x) { return x == this.a or x == this.b or x == this.c; }
+            }
+            var mySet = Set(1, 3, 5);
+        ";
+
+        assert_eq!(eval(&format!("{source} 3 in mySet;")).unwrap(), "true");
+        assert_eq!(eval(&format!("{source} 4 in mySet;")).unwrap(), "false");
+        assert_eq!(
+            eval("class Empty {} 1 in Empty();")
+                .unwrap_err()
+                .to_string(),
+            "[line 1] Undefined property 'contains'."
+        );
+    }
+
+    #[test]
+    fn in_tests_ranges_and_strings() {
+        assert_eq!(eval("3 in 0..5;").unwrap(), "true");
+        assert_eq!(eval("5 in 0..5;").unwrap(), "false");
+        assert_eq!(eval("5 in 0..=5;").unwrap(), "true");
+        assert_eq!(eval("\"b\" in 0..5;").unwrap(), "false");
+        assert_eq!(eval("\"ell\" in \"hello\";").unwrap(), "true");
+        assert_eq!(
+            eval("1 in 2;").unwrap_err().to_string(),
+            "[line 1] Can only test membership in ranges, strings and instances."
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use super::callable::{NativeFn, NativeMethod};
+use super::{Interpreter, RuntimeError};
+use crate::value::{Range, Value};
+use anyhow::Result;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The native functions defined in every interpreter's global scope, as
+// (name, fewest arguments, most arguments, function). Adding a native only
+// takes an entry here.
+pub const NATIVES: &[(&str, usize, usize, NativeFn)] = &[
+    ("approx_eq", 3, 3, approx_eq),
+    ("atan2", 2, 2, atan2),
+    ("clock", 0, 0, clock),
+    ("cos", 1, 1, cos),
+    ("exit", 1, 1, exit),
+    ("exp", 1, 1, exp),
+    ("hypot", 2, 2, hypot),
+    ("log", 1, 2, log),
+    ("panic", 1, 1, panic),
+    ("sin", 1, 1, sin),
+    ("tan", 1, 1, tan),
+];
+
+// whether two numbers are at most `epsilon` apart, as `==` is for floating
+// point numbers only when they come out exactly the same
+fn approx_eq(interpreter: &Interpreter, arguments: &[Value]) -> Result<Value> {
+    let [a, b, epsilon] = numbers(interpreter, "approx_eq", arguments)?[..] else {
+        unreachable!("approx_eq takes 3 arguments");
+    };
+
+    Ok(Value::Bool((a - b).abs() <= epsilon))
+}
+
+// the angle of the point (x, y), in radians
+fn atan2(interpreter: &Interpreter, arguments: &[Value]) -> Result<Value> {
+    let [y, x] = numbers(interpreter, "atan2", arguments)?[..] else {
+        unreachable!("atan2 takes 2 arguments");
+    };
+
+    math(interpreter, "atan2", y.atan2(x))
+}
+
+fn cos(interpreter: &Interpreter, arguments: &[Value]) -> Result<Value> {
+    unary(interpreter, "cos", arguments, f64::cos)
+}
+
+// ends the script with the exit code, once the finally blocks it is in ran
+fn exit(interpreter: &Interpreter, arguments: &[Value]) -> Result<Value> {
+    match arguments[0] {
+        Value::Number(n) if n.fract() == 0.0 && (0.0..=255.0).contains(&n) => {
+            Err(RuntimeError::Exit(n as u8))?
+        }
+        _ => Err(RuntimeError::InvalidExitCode(interpreter.call_line()))?,
+    }
+}
+
+fn exp(interpreter: &Interpreter, arguments: &[Value]) -> Result<Value> {
+    unary(interpreter, "exp", arguments, f64::exp)
+}
+
+// the length of the hypotenuse, without overflowing for large sides
+fn hypot(interpreter: &Interpreter, arguments: &[Value]) -> Result<Value> {
+    let [x, y] = numbers(interpreter, "hypot", arguments)?[..] else {
+        unreachable!("hypot takes 2 arguments");
+    };
+
+    math(interpreter, "hypot", x.hypot(y))
+}
+
+// the natural logarithm of x, or its logarithm in the given base
+fn log(interpreter: &Interpreter, arguments: &[Value]) -> Result<Value> {
+    let log = match numbers(interpreter, "log", arguments)?[..] {
+        [x] => x.ln(),
+        [x, base] => x.log(base),
+        _ => unreachable!("log takes 1 or 2 arguments"),
+    };
+
+    math(interpreter, "log", log)
+}
+
+// ends the script with an error no catch or finally block can intercept
+fn panic(interpreter: &Interpreter, arguments: &[Value]) -> Result<Value> {
+    Err(RuntimeError::Panic(
+        interpreter.call_line(),
+        arguments[0].to_string(),
+    ))?
+}
+
+fn sin(interpreter: &Interpreter, arguments: &[Value]) -> Result<Value> {
+    unary(interpreter, "sin", arguments, f64::sin)
+}
+
+fn tan(interpreter: &Interpreter, arguments: &[Value]) -> Result<Value> {
+    unary(interpreter, "tan", arguments, f64::tan)
+}
+
+fn unary(
+    interpreter: &Interpreter,
+    name: &str,
+    arguments: &[Value],
+    function: fn(f64) -> f64,
+) -> Result<Value> {
+    let [x] = numbers(interpreter, name, arguments)?[..] else {
+        unreachable!("{} takes 1 argument", name);
+    };
+
+    math(interpreter, name, function(x))
+}
+
+// A math function's result. NaN means an argument was outside the
+// function's domain, like log(-1), which strict arithmetic makes an error
+// along with infinite results.
+fn math(interpreter: &Interpreter, name: &str, result: f64) -> Result<Value> {
+    if interpreter.strict_arithmetic && result.is_nan() {
+        Err(RuntimeError::OutOfDomain(
+            interpreter.call_line(),
+            name.to_string(),
+        ))?
+    }
+
+    interpreter.finite(Value::Number(result), interpreter.call_line())
+}
+
+// seconds since the Unix epoch, for timing Lox code
+fn clock(_: &Interpreter, _: &[Value]) -> Result<Value> {
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH)?;
+
+    Ok(Value::Number(elapsed.as_secs_f64()))
+}
+
+// the arguments of the native `name`, which only takes numbers
+fn numbers(interpreter: &Interpreter, name: &str, arguments: &[Value]) -> Result<Vec<f64>> {
+    arguments
+        .iter()
+        .map(|argument| match argument {
+            Value::Number(n) => Ok(*n),
+            _ => Err(
+                RuntimeError::ArgumentsNotNumbers(interpreter.call_line(), name.to_string()).into(),
+            ),
+        })
+        .collect()
+}
+
+// a range's `length` and its `contains(x)` method, which is false for
+// anything but an integer in the range
+pub fn range_property(range: Range, name: &str) -> Option<Value> {
+    match name {
+        "length" => Some(Value::Number(range.length() as f64)),
+        "contains" => {
+            let contains = move |arguments: &[Value]| {
+                let found = matches!(arguments[0], Value::Number(n) if range.contains(n));
+                Ok(Value::Bool(found))
+            };
+
+            let method = NativeMethod::new(name.into(), 1, Box::new(contains));
+            Some(Value::Callable(Rc::new(method)))
+        }
+        _ => None,
+    }
+}
@@ -1,66 +1,422 @@
-mod ast;
+mod diagnostics;
+mod interp;
+mod optimize;
+mod parse;
+mod resolve;
 mod scan;
+mod value;
 
 use anyhow::Result;
 use std::env;
 use std::fs;
 use std::io;
 use std::io::BufRead;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+const USAGE: &str = "Usage: rlox [options] [script]
+
+Options:
+    --encoding <utf-8|latin1>  source file encoding (default utf-8)
+    --define <FLAG>            enable `//#if FLAG` regions, may be repeated
+    --max-errors <N>           report at most N errors (default 20)
+    --allow <lint>             don't report lint, may be repeated
+    --warn <lint>              report lint as a warning, may be repeated
+    --deny <lint>              report lint as an error, may be repeated
+    --catch-runtime-errors     let catch handle runtime errors, not only thrown values
+    --strict-arithmetic        make infinite and NaN results runtime errors
+    --private-members          allow members named with a leading '_' only on 'this'
+    --time                     print the time spent in each phase to stderr
+    --trace                    print each statement to stderr as it runs
+    --tokens                   print the scanned tokens instead of running
+    --ast                      print the parsed statements instead of running
+    --emit-tokens-csv          print the scanned tokens as CSV
+    --syntax-only <dir>        check every .lox file under dir without running it
+    --repl-load <file>         run file before starting the REPL, keeping its definitions";
+
 #[derive(Error, Debug)]
 pub enum CLIError {
     #[error("Too many arguments")]
     TooManyArguments,
+
+    #[error("Unknown option '{0}'")]
+    UnknownOption(String),
+
+    #[error("Missing value for '{0}'")]
+    MissingValue(String),
+
+    #[error("Unknown encoding '{0}'")]
+    UnknownEncoding(String),
+
+    #[error("Invalid error count '{0}'")]
+    InvalidMaxErrors(String),
+
+    #[error("Unknown lint '{0}'")]
+    UnknownLint(String),
+
+    #[error("{0} file(s) with syntax errors")]
+    SyntaxErrors(usize),
+}
+
+struct Options {
+    script: Option<String>,
+    encoding: scan::Encoding,
+    time: bool,
+    trace: bool,
+    strict_arithmetic: bool,
+    catch_runtime_errors: bool,
+    private_members: bool,
+    defines: Vec<String>,
+    max_errors: usize,
+    lints: diagnostics::Lints,
+    syntax_only: Option<String>,
+    repl_load: Option<String>,
+    tokens: bool,
+    tokens_csv: bool,
+    ast: bool,
 }
 
+// The interpreter recurses for every nested call, block and expression, so
+// it runs on a thread with a large stack. It reports a stack overflow as a
+// runtime error before using all of it.
 fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
+    let cli = thread::Builder::new()
+        .stack_size(interp::STACK_SIZE)
+        .spawn(cli)?;
 
-    if args.len() > 2 {
-        println!("Usage: rlox [script]");
+    match cli.join() {
+        Ok(Err(e)) => match interp::exit_status(&e) {
+            Some((code, report)) => {
+                if report {
+                    eprintln!("Error: {:?}", e);
+                }
 
-        Err(CLIError::TooManyArguments)?;
+                process::exit(code);
+            }
+            None => Err(e),
+        },
+        Ok(result) => result,
+        Err(e) => panic::resume_unwind(e),
     }
+}
+
+fn cli() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    if args.len() == 2 {
-        run_file(&args[1])?;
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}", USAGE);
+
+            return Err(e);
+        }
+    };
+
+    if let Some(dir) = &options.syntax_only {
+        return check_dir(dir, &options);
+    }
+
+    if let Some(script) = &options.script {
+        run_file(script, &mut interpreter(&options), &options)?;
 
         return Ok(());
     }
 
     println!("Starting REPL");
 
-    run_prompt()?;
+    run_prompt(&options)?;
 
-    return Ok(());
+    Ok(())
 }
 
-fn run_prompt() -> Result<()> {
+fn interpreter(options: &Options) -> interp::Interpreter {
+    interp::Interpreter::new()
+        .with_strict_arithmetic(options.strict_arithmetic)
+        .with_catch_runtime_errors(options.catch_runtime_errors)
+        .with_private_members(options.private_members)
+}
+
+fn parse_args(args: &[String]) -> Result<Options> {
+    let mut options = Options {
+        script: None,
+        encoding: scan::Encoding::Utf8,
+        time: false,
+        trace: false,
+        strict_arithmetic: false,
+        catch_runtime_errors: false,
+        private_members: false,
+        defines: Vec::new(),
+        max_errors: diagnostics::DEFAULT_MAX_ERRORS,
+        lints: diagnostics::Lints::default(),
+        syntax_only: None,
+        repl_load: None,
+        tokens: false,
+        tokens_csv: false,
+        ast: false,
+    };
+
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--encoding" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| CLIError::MissingValue(arg.clone()))?;
+
+                options.encoding = match value.to_lowercase().as_str() {
+                    "utf-8" | "utf8" => scan::Encoding::Utf8,
+                    "latin1" | "latin-1" | "iso-8859-1" => scan::Encoding::Latin1,
+                    _ => Err(CLIError::UnknownEncoding(value.clone()))?,
+                };
+            }
+            "--define" => {
+                let flag = args
+                    .next()
+                    .ok_or_else(|| CLIError::MissingValue(arg.clone()))?;
+
+                options.defines.push(flag.clone());
+            }
+            "--max-errors" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| CLIError::MissingValue(arg.clone()))?;
+
+                options.max_errors = value
+                    .parse()
+                    .map_err(|_| CLIError::InvalidMaxErrors(value.clone()))?;
+            }
+            "--allow" | "--warn" | "--deny" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| CLIError::MissingValue(arg.clone()))?;
+                let lint = diagnostics::Lint::from_name(name)
+                    .ok_or_else(|| CLIError::UnknownLint(name.clone()))?;
+
+                let level = match arg.as_str() {
+                    "--allow" => diagnostics::Level::Allow,
+                    "--warn" => diagnostics::Level::Warn,
+                    _ => diagnostics::Level::Deny,
+                };
+
+                options.lints.set(lint, level);
+            }
+            "--time" => options.time = true,
+            "--trace" => options.trace = true,
+            "--strict-arithmetic" => options.strict_arithmetic = true,
+            "--catch-runtime-errors" => options.catch_runtime_errors = true,
+            "--private-members" => options.private_members = true,
+            "--tokens" => options.tokens = true,
+            "--emit-tokens-csv" => options.tokens_csv = true,
+            "--ast" => options.ast = true,
+            "--syntax-only" => {
+                let dir = args
+                    .next()
+                    .ok_or_else(|| CLIError::MissingValue(arg.clone()))?;
+
+                options.syntax_only = Some(dir.clone());
+            }
+            "--repl-load" => {
+                let file = args
+                    .next()
+                    .ok_or_else(|| CLIError::MissingValue(arg.clone()))?;
+
+                options.repl_load = Some(file.clone());
+            }
+            _ if arg.starts_with("--") => Err(CLIError::UnknownOption(arg.clone()))?,
+            _ if options.script.is_none() => options.script = Some(arg.clone()),
+            _ => Err(CLIError::TooManyArguments)?,
+        }
+    }
+
+    Ok(options)
+}
+
+fn run_prompt(options: &Options) -> Result<()> {
+    let mut interpreter = interpreter(options);
+
+    // what the file defined before any error stays available at the prompt
+    if let Some(path) = &options.repl_load {
+        match run_file(path, &mut interpreter, options) {
+            Err(e) if interp::is_fatal(&e) => return Err(e),
+            Err(e) => eprintln!("Error: {}", e),
+            Ok(()) => {}
+        }
+    }
+
     let stdin = io::stdin();
     for line in stdin.lock().lines() {
-        run(&line?)?;
+        let scanner = scan::Scanner::new(line?)
+            .with_defines(&options.defines)
+            .with_max_errors(options.max_errors);
+
+        // a mistake at the prompt shouldn't end the session
+        match run(scanner, &mut interpreter, options, true) {
+            Err(e) if interp::is_fatal(&e) => return Err(e),
+            Err(e) => eprintln!("Error: {}", e),
+            Ok(()) => {}
+        }
     }
 
     Ok(())
 }
 
-fn run_file(path: &str) -> Result<()> {
-    run(&fs::read_to_string(path)?)?;
+fn run_file(path: &str, interpreter: &mut interp::Interpreter, options: &Options) -> Result<()> {
+    run(
+        scan::Scanner::from_bytes(&fs::read(path)?, options.encoding)?
+            .with_defines(&options.defines)
+            .with_max_errors(options.max_errors),
+        interpreter,
+        options,
+        false,
+    )?;
 
     Ok(())
 }
 
-fn run(source: &str) -> Result<()> {
-    println!("running {}", source);
+// `repl` echoes the value of a line holding a single expression statement
+fn run(
+    mut scanner: scan::Scanner,
+    interpreter: &mut interp::Interpreter,
+    options: &Options,
+    repl: bool,
+) -> Result<()> {
+    let mut timings: Vec<(&str, Duration)> = Vec::new();
 
-    let mut scanner = scan::Scanner::new(source.to_string());
+    if options.trace {
+        let offset = interpreter.trace(scanner.source());
+        scanner = scanner.with_offset(offset);
+    }
 
+    let start = Instant::now();
     let tokens = scanner.scan_tokens()?;
+    timings.push(("scan", start.elapsed()));
+
+    if options.tokens_csv {
+        println!("{}", scan::token::Token::CSV_HEADER);
+
+        for token in &tokens {
+            println!("{}", token.to_csv());
+        }
+    } else if options.tokens {
+        for token in &tokens {
+            println!("{}", token);
+        }
+    } else {
+        let start = Instant::now();
+        let statements = parse::Parser::new(tokens)
+            .with_max_errors(options.max_errors)
+            .parse()?;
+        timings.push(("parse", start.elapsed()));
+
+        if options.ast {
+            for statement in &statements {
+                println!("{}", statement);
+            }
+        } else {
+            let start = Instant::now();
+            let mut resolver = resolve::Resolver::new()
+                .with_max_errors(options.max_errors)
+                .with_lints(options.lints.clone());
+            let resolved = resolver.resolve(&statements);
+            timings.push(("resolve", start.elapsed()));
+
+            for warning in resolver.take_warnings() {
+                eprintln!("{}", warning);
+            }
+
+            resolved?;
+
+            // after resolving, so dead code is still checked and linted as
+            // written. Removing a branch doesn't change any scopes.
+            let start = Instant::now();
+            let mut optimizer = optimize::Optimizer::new()
+                .with_max_errors(options.max_errors)
+                .with_lints(options.lints.clone());
+            let optimized = optimizer.optimize(statements);
+            timings.push(("optimize", start.elapsed()));
+
+            for warning in optimizer.take_warnings() {
+                eprintln!("{}", warning);
+            }
 
-    for token in tokens {
-        println!("{}", token);
+            let statements = optimized?;
+
+            let start = Instant::now();
+
+            match statements.as_slice() {
+                [parse::ast::Stmt {
+                    kind: parse::ast::StmtKind::Expression(expr),
+                    ..
+                }] if repl => {
+                    println!("{}", interpreter.evaluate(expr)?)
+                }
+                _ => interpreter.interpret(&statements)?,
+            }
+
+            timings.push(("interpret", start.elapsed()));
+        }
+    }
+
+    if options.time {
+        for (phase, duration) in timings {
+            eprintln!("{}: {:?}", phase, duration);
+        }
     }
 
     Ok(())
 }
+
+fn check_dir(dir: &str, options: &Options) -> Result<()> {
+    let mut files = Vec::new();
+    collect_lox_files(Path::new(dir), &mut files)?;
+    files.sort();
+
+    let mut failed = 0;
+
+    for path in &files {
+        if let Err(e) = check_file(path, options) {
+            println!("{}: {}", path.display(), e);
+            failed += 1;
+        }
+    }
+
+    println!("{} files checked, {} with errors", files.len(), failed);
+
+    if failed > 0 {
+        Err(CLIError::SyntaxErrors(failed))?;
+    }
+
+    Ok(())
+}
+
+fn collect_lox_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_lox_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "lox") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn check_file(path: &Path, options: &Options) -> Result<()> {
+    let tokens = scan::Scanner::from_bytes(&fs::read(path)?, options.encoding)?
+        .with_defines(&options.defines)
+        .with_max_errors(options.max_errors)
+        .scan_tokens()?;
+
+    parse::Parser::new(tokens)
+        .with_max_errors(options.max_errors)
+        .parse()?;
+
+    Ok(())
+}
@@ -0,0 +1,281 @@
+use crate::diagnostics::{self, Level, Lint, Lints};
+use crate::interp;
+use crate::parse::ast::{Expr, Function, Stmt, StmtKind};
+use crate::scan::token::TokenType;
+use crate::value::Value;
+use anyhow::Result;
+use std::rc::Rc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+enum OptimizerError {
+    #[error("{}", .0.join("\n"))]
+    Errors(Vec<String>),
+}
+
+// Runs between resolving and interpreting. An `if` or `while` whose condition is
+// a constant loses the branch that can never run. Only conditions made of
+// literals are constant, so removing one never skips a side effect.
+pub struct Optimizer {
+    lints: Lints,
+    warnings: Vec<String>, // lints found at the warning level
+    errors: Vec<String>,
+    max_errors: usize, // how many of `errors` are reported
+}
+
+impl Optimizer {
+    pub fn new() -> Self {
+        Optimizer {
+            lints: Lints::default(),
+            warnings: Vec::new(),
+            errors: Vec::new(),
+            max_errors: diagnostics::DEFAULT_MAX_ERRORS,
+        }
+    }
+
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    pub fn with_lints(mut self, lints: Lints) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    // the warnings found so far, which don't stop the script from running
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub fn optimize(&mut self, statements: Vec<Stmt>) -> Result<Vec<Stmt>> {
+        let statements = self.statements(statements);
+
+        if !self.errors.is_empty() {
+            let errors = std::mem::take(&mut self.errors);
+            Err(OptimizerError::Errors(diagnostics::cap(
+                errors,
+                self.max_errors,
+            )))?;
+        }
+
+        Ok(statements)
+    }
+
+    fn statements(&mut self, statements: Vec<Stmt>) -> Vec<Stmt> {
+        statements
+            .into_iter()
+            .map(|statement| self.statement(statement))
+            .collect()
+    }
+
+    fn statement(&mut self, stmt: Stmt) -> Stmt {
+        let kind = match stmt.kind {
+            StmtKind::Block(statements) => StmtKind::Block(self.statements(statements)),
+            StmtKind::Class {
+                name,
+                superclass,
+                methods,
+            } => StmtKind::Class {
+                name,
+                superclass,
+                methods: methods
+                    .into_iter()
+                    .map(|method| self.function(method))
+                    .collect(),
+            },
+            StmtKind::ForIn {
+                name,
+                iterable,
+                body,
+            } => StmtKind::ForIn {
+                name,
+                iterable,
+                body: Box::new(self.statement(*body)),
+            },
+            StmtKind::Defer(body) => StmtKind::Defer(Box::new(self.statement(*body))),
+            StmtKind::Function(function) => StmtKind::Function(self.function(function)),
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => match constant(&condition).map(|value| value.is_truthy()) {
+                Some(true) => {
+                    if else_branch.is_some() {
+                        self.lint(
+                            stmt.line,
+                            "Condition is always true, the else branch never runs.",
+                        );
+                    }
+
+                    return self.statement(*then_branch);
+                }
+                Some(false) => {
+                    self.lint(
+                        stmt.line,
+                        "Condition is always false, the branch never runs.",
+                    );
+
+                    match else_branch {
+                        Some(else_branch) => return self.statement(*else_branch),
+                        None => StmtKind::Empty,
+                    }
+                }
+                None => StmtKind::If {
+                    condition,
+                    then_branch: Box::new(self.statement(*then_branch)),
+                    else_branch: else_branch.map(|branch| Box::new(self.statement(*branch))),
+                },
+            },
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => StmtKind::Try {
+                body: self.statements(body),
+                catch: catch.map(|(name, catch)| (name, self.statements(catch))),
+                finally: finally.map(|finally| self.statements(finally)),
+            },
+            // an endless `while (true)` is left for a `return` to leave
+            StmtKind::While {
+                condition,
+                body,
+                increment,
+            } => match constant(&condition).map(|value| value.is_truthy()) {
+                Some(false) => {
+                    self.lint(stmt.line, "Condition is always false, the loop never runs.");
+
+                    StmtKind::Empty
+                }
+                _ => StmtKind::While {
+                    condition,
+                    body: Box::new(self.statement(*body)),
+                    increment,
+                },
+            },
+            kind => kind,
+        };
+
+        Stmt { kind, ..stmt }
+    }
+
+    // the parser's functions aren't shared yet, so their bodies can be
+    // taken apart
+    fn function(&mut self, function: Rc<Function>) -> Rc<Function> {
+        match Rc::try_unwrap(function) {
+            Ok(mut function) => {
+                function.body = self.statements(function.body);
+                Rc::new(function)
+            }
+            Err(function) => function,
+        }
+    }
+
+    fn lint(&mut self, line: u32, message: &str) {
+        match self.lints.report(Lint::DeadBranch, line, message) {
+            Some((Level::Deny, report)) => self.errors.push(report),
+            Some((_, report)) => self.warnings.push(report),
+            None => {}
+        }
+    }
+}
+
+// The value of an expression made only of literals, folded the way the
+// interpreter would evaluate it. Anything else, including an expression that
+// would be a runtime error, isn't constant. Neither is an infinite or NaN
+// result, which is an error in strict arithmetic.
+fn constant(expr: &Expr) -> Option<Value> {
+    match expr {
+        Expr::Literal(literal) => Some(literal.into()),
+        Expr::Grouping(expr) => constant(expr),
+        Expr::Unary { operator, right } => match (operator.r#type, constant(right)?) {
+            (TokenType::Minus, Value::Number(n)) => Some(Value::Number(-n)),
+            (TokenType::Minus, _) => None,
+            (_, right) => Some(Value::Bool(!right.is_truthy())),
+        },
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => interp::binary(operator, constant(left)?, constant(right)?)
+            .ok()
+            .filter(|value| !matches!(value, Value::Number(n) if !n.is_finite())),
+        Expr::Logical {
+            left,
+            operator,
+            right,
+        } => {
+            let left = constant(left)?;
+
+            match (operator.r#type, left.is_truthy()) {
+                (TokenType::Or, true) | (TokenType::And, false) => Some(left),
+                _ => constant(right),
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::scan::Scanner;
+
+    // the optimized statements, printed one per line, and the warnings
+    fn optimize(source: &str) -> (String, Vec<String>) {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let mut optimizer = Optimizer::new();
+        let statements = optimizer.optimize(statements).unwrap();
+
+        let printed: Vec<String> = statements.iter().map(|stmt| stmt.to_string()).collect();
+
+        (printed.join("\n"), optimizer.take_warnings())
+    }
+
+    #[test]
+    fn removes_an_always_false_branch() {
+        let (printed, warnings) = optimize("if (false) { print 1; }\nprint 2;");
+
+        assert_eq!(printed, "(;)\n(print 2)");
+        assert_eq!(
+            warnings,
+            ["[line 1] Warning: Condition is always false, the branch never runs. [dead-branch]"]
+        );
+    }
+
+    #[test]
+    fn keeps_the_branch_that_runs() {
+        let (printed, warnings) = optimize("if (!true) print 1; else print 2;");
+        assert_eq!(printed, "(print 2)");
+        assert_eq!(warnings.len(), 1);
+
+        let (printed, warnings) = optimize("if (1 < 2 and nil == nil) print 1; else print 2;");
+        assert_eq!(printed, "(print 1)");
+        assert_eq!(
+            warnings,
+            ["[line 1] Warning: Condition is always true, the else branch never runs. [dead-branch]"]
+        );
+    }
+
+    #[test]
+    fn removes_an_always_false_loop() {
+        let (printed, warnings) = optimize("fun f() { while (false) print 1; }");
+        assert_eq!(printed, "(fun f () (;))");
+        assert_eq!(warnings.len(), 1);
+
+        let (printed, warnings) = optimize("while (true) { print 1; }");
+        assert_eq!(printed, "(while true (block (print 1)))");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn keeps_conditions_that_are_not_constant() {
+        let source = "var a = false; fun f() { return false; } if (a) print 1; if (f()) print 2; if (a = false) print 3;";
+        let (printed, warnings) = optimize(source);
+
+        assert_eq!(printed.matches("(if").count(), 3);
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+use crate::scan::token::{Literal, Token};
+use std::cell::Cell;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+// How many scopes out from a use its variable is declared. The resolver
+// fills it in after parsing; it stays None for globals.
+pub type Depth = Cell<Option<usize>>;
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Assign {
+        name: Token,
+        value: Box<Expr>,
+        depth: Depth,
+    },
+    Binary {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        paren: Token, // the closing paren, kept for error locations
+        arguments: Vec<Expr>,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    Grouping(Box<Expr>),
+    Literal(Literal),
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+    },
+    Super {
+        keyword: Token,
+        method: Token,
+        depth: Depth,
+    },
+    This {
+        keyword: Token,
+        depth: Depth,
+    },
+    Unary {
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Variable {
+        name: Token,
+        depth: Depth,
+        // how many scopes out `this` is, inside a method, for a name that
+        // may be a member of the instance
+        receiver: Depth,
+    },
+}
+
+// a statement and where it came from in the source
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub line: u32,          // where the statement starts
+    pub span: Range<usize>, // byte offsets of the statement's source
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
+    Block(Vec<Stmt>),
+    Class {
+        name: Token,
+        superclass: Option<Expr>, // always a variable
+        methods: Vec<Rc<Function>>,
+    },
+    Continue,
+    Defer(Box<Stmt>), // run when the enclosing block or function exits
+    Empty,            // a lone `;`
+    Expression(Expr),
+    ForIn {
+        name: Token,
+        iterable: Expr,
+        body: Box<Stmt>,
+    },
+    Function(Rc<Function>),
+    // `global name = value;`, assigning the global even where a local by
+    // that name is in scope, and defining it if there is none
+    Global {
+        name: Token,
+        value: Expr,
+    },
+    If {
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+    },
+    Print(Expr),
+    Return(Option<Expr>),
+    Throw {
+        keyword: Token, // for the line of the error
+        value: Expr,
+    },
+    Try {
+        body: Vec<Stmt>,
+        catch: Option<(Token, Vec<Stmt>)>, // the name the caught value is bound to
+        finally: Option<Vec<Stmt>>,
+    },
+    Var {
+        name: Token,
+        initializer: Option<Expr>,
+    },
+    While {
+        condition: Expr,
+        body: Box<Stmt>,
+        increment: Option<Expr>, // of a `for`, run after the body and on `continue`
+    },
+}
+
+// shared between the declaration statement and every closure created from it
+#[derive(Debug)]
+pub struct Function {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub defaults: Vec<Expr>, // of the trailing parameters that have one
+    pub body: Vec<Stmt>,
+}
+
+impl Function {
+    // the number of parameters without a default
+    pub fn required(&self) -> usize {
+        self.params.len() - self.defaults.len()
+    }
+}
+
+// Prints the tree as a fully parenthesized S-expression, e.g. `(* (- 1) (group 2))`.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Assign { name, value, .. } => write!(f, "(= {} {})", name.lexeme, value),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => write!(f, "({} {} {})", operator.lexeme, left, right),
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                write!(f, "(call {}", callee)?;
+
+                for argument in arguments {
+                    write!(f, " {}", argument)?;
+                }
+
+                write!(f, ")")
+            }
+            Expr::Get { object, name } => write!(f, "(. {} {})", object, name.lexeme),
+            Expr::Grouping(expr) => write!(f, "(group {})", expr),
+            // quoted, so `"1"` and `1` print differently
+            Expr::Literal(Literal::String(s)) => write!(f, "{:?}", s),
+            Expr::Literal(literal) => write!(f, "{}", literal),
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => write!(f, "({} {} {})", operator.lexeme, left, right),
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => write!(f, "(= (. {} {}) {})", object, name.lexeme, value),
+            Expr::Super { method, .. } => write!(f, "(super {})", method.lexeme),
+            Expr::This { .. } => write!(f, "this"),
+            Expr::Unary { operator, right } => write!(f, "({} {})", operator.lexeme, right),
+            Expr::Variable { name, .. } => write!(f, "{}", name.lexeme),
+        }
+    }
+}
+
+impl fmt::Display for StmtKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StmtKind::Block(statements) => {
+                write!(f, "(block")?;
+
+                for statement in statements {
+                    write!(f, " {}", statement)?;
+                }
+
+                write!(f, ")")
+            }
+            StmtKind::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                write!(f, "(class {}", name.lexeme)?;
+
+                if let Some(superclass) = superclass {
+                    write!(f, " < {}", superclass)?;
+                }
+
+                for method in methods {
+                    write!(f, " {}", method)?;
+                }
+
+                write!(f, ")")
+            }
+            StmtKind::Continue => write!(f, "(continue)"),
+            StmtKind::Defer(body) => write!(f, "(defer {})", body),
+            StmtKind::Empty => write!(f, "(;)"),
+            StmtKind::Expression(expr) => write!(f, "(; {})", expr),
+            StmtKind::ForIn {
+                name,
+                iterable,
+                body,
+            } => write!(f, "(for {} {} {})", name.lexeme, iterable, body),
+            StmtKind::Function(function) => write!(f, "{}", function),
+            StmtKind::Global { name, value } => write!(f, "(global {} {})", name.lexeme, value),
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch: Some(else_branch),
+            } => write!(f, "(if {} {} {})", condition, then_branch, else_branch),
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch: None,
+            } => write!(f, "(if {} {})", condition, then_branch),
+            StmtKind::Print(expr) => write!(f, "(print {})", expr),
+            StmtKind::Return(Some(value)) => write!(f, "(return {})", value),
+            StmtKind::Return(None) => write!(f, "(return)"),
+            StmtKind::Throw { value, .. } => write!(f, "(throw {})", value),
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                write!(f, "(try (block")?;
+
+                for statement in body {
+                    write!(f, " {}", statement)?;
+                }
+
+                write!(f, ")")?;
+
+                if let Some((name, catch)) = catch {
+                    write!(f, " (catch {}", name.lexeme)?;
+
+                    for statement in catch {
+                        write!(f, " {}", statement)?;
+                    }
+
+                    write!(f, ")")?;
+                }
+
+                if let Some(finally) = finally {
+                    write!(f, " (finally")?;
+
+                    for statement in finally {
+                        write!(f, " {}", statement)?;
+                    }
+
+                    write!(f, ")")?;
+                }
+
+                write!(f, ")")
+            }
+            StmtKind::Var {
+                name,
+                initializer: Some(initializer),
+            } => write!(f, "(var {} {})", name.lexeme, initializer),
+            StmtKind::Var {
+                name,
+                initializer: None,
+            } => write!(f, "(var {})", name.lexeme),
+            StmtKind::While {
+                condition,
+                body,
+                increment: Some(increment),
+            } => write!(f, "(while {} {} {})", condition, body, increment),
+            StmtKind::While {
+                condition,
+                body,
+                increment: None,
+            } => write!(f, "(while {} {})", condition, body),
+        }
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(fun {} (", self.name.lexeme)?;
+
+        for (i, param) in self.params.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            match i.checked_sub(self.required()) {
+                Some(default) => write!(f, "(= {} {})", param.lexeme, self.defaults[default])?,
+                None => write!(f, "{}", param.lexeme)?,
+            }
+        }
+
+        write!(f, ")")?;
+
+        for statement in &self.body {
+            write!(f, " {}", statement)?;
+        }
+
+        write!(f, ")")
+    }
+}
@@ -0,0 +1,980 @@
+pub mod ast;
+
+use crate::diagnostics;
+use crate::scan::token::{Literal, Token, TokenType};
+use anyhow::Result;
+use std::rc::Rc;
+use thiserror::Error;
+
+const MAX_ARGUMENTS: usize = 255;
+
+#[derive(Error, Debug)]
+enum ParserError {
+    #[error("[line {line}] Error at {at}: {message}")]
+    Syntax {
+        line: u32,
+        at: String,
+        message: String,
+    },
+
+    #[error("{}", .0.join("\n"))]
+    Errors(Vec<String>),
+}
+
+// the kind of function whose body is being parsed, deciding where `return`
+// is allowed
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionKind {
+    None,
+    Function,
+    Initializer,
+    Deferred, // a deferred statement, which runs after its function returned
+}
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    current: usize, // points to the next token to be consumed
+    function: FunctionKind,
+    loops: usize,        // loops around the statement being parsed, within the function
+    errors: Vec<String>, // syntax errors recovered from so far
+    max_errors: usize,   // how many of them are reported
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Parser {
+            tokens,
+            current: 0,
+            function: FunctionKind::None,
+            loops: 0,
+            errors: Vec::new(),
+            max_errors: diagnostics::DEFAULT_MAX_ERRORS,
+        }
+    }
+
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    // reports every syntax error in the source, not just the first
+    pub fn parse(&mut self) -> Result<Vec<ast::Stmt>> {
+        let mut statements = Vec::new();
+
+        while !self.is_at_end() {
+            statements.extend(self.declaration());
+        }
+
+        if !self.errors.is_empty() {
+            let errors = std::mem::take(&mut self.errors);
+            Err(ParserError::Errors(diagnostics::cap(
+                errors,
+                self.max_errors,
+            )))?;
+        }
+
+        Ok(statements)
+    }
+
+    // parses source that must hold a single expression and nothing else
+    pub fn parse_expression(&mut self) -> Result<ast::Expr> {
+        let expr = self.expression()?;
+
+        if !self.is_at_end() {
+            Err(self.error(self.peek(), "Expect end of expression."))?;
+        }
+
+        Ok(expr)
+    }
+
+    // a declaration with a syntax error is recorded and skipped, and parsing
+    // resumes at the next statement
+    fn declaration(&mut self) -> Option<ast::Stmt> {
+        match self.try_declaration() {
+            Ok(stmt) => Some(stmt),
+            Err(e) => {
+                self.errors.push(e.to_string());
+                self.synchronize();
+
+                None
+            }
+        }
+    }
+
+    fn try_declaration(&mut self) -> Result<ast::Stmt> {
+        let start = self.current;
+
+        let kind = if self.match_types(&[TokenType::Class]) {
+            self.class_declaration()?
+        } else if self.match_types(&[TokenType::Fun]) {
+            ast::StmtKind::Function(self.function("function")?)
+        } else if self.match_types(&[TokenType::Var]) {
+            self.var_declaration()?
+        } else {
+            return self.statement();
+        };
+
+        Ok(self.spanned(start, kind))
+    }
+
+    fn class_declaration(&mut self) -> Result<ast::StmtKind> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect class name.")?
+            .clone();
+
+        let mut superclass = None;
+
+        if self.match_types(&[TokenType::Less]) {
+            let token = self
+                .consume(TokenType::Identifier, "Expect superclass name.")?
+                .clone();
+
+            if token.lexeme == name.lexeme {
+                Err(self.error(&token, "A class can't inherit from itself."))?;
+            }
+
+            superclass = Some(ast::Expr::Variable {
+                name: token,
+                depth: ast::Depth::default(),
+                receiver: ast::Depth::default(),
+            });
+        }
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(ast::StmtKind::Class {
+            name,
+            superclass,
+            methods,
+        })
+    }
+
+    // `kind` names what is being declared in error messages
+    fn function(&mut self, kind: &str) -> Result<Rc<ast::Function>> {
+        let name = self
+            .consume(TokenType::Identifier, &format!("Expect {} name.", kind))?
+            .clone();
+
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {} name.", kind),
+        )?;
+
+        let mut params = Vec::new();
+        let mut defaults = Vec::new();
+
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    Err(self.error(self.peek(), "Can't have more than 255 parameters."))?;
+                }
+
+                let param = self
+                    .consume(TokenType::Identifier, "Expect parameter name.")?
+                    .clone();
+
+                // once a parameter has a default, the ones after it need one
+                if self.match_types(&[TokenType::Equal]) {
+                    defaults.push(self.expression()?);
+                } else if !defaults.is_empty() {
+                    Err(self.error(
+                        &param,
+                        "Expect a default value after a defaulted parameter.",
+                    ))?;
+                }
+
+                params.push(param);
+
+                if !self.match_types(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+
+        let enclosing = (self.function, self.loops);
+        self.function = match (kind, &*name.lexeme) {
+            ("method", "init") => FunctionKind::Initializer,
+            _ => FunctionKind::Function,
+        };
+        self.loops = 0;
+
+        let body = self.block();
+
+        (self.function, self.loops) = enclosing;
+
+        Ok(Rc::new(ast::Function {
+            name,
+            params,
+            defaults,
+            body: body?,
+        }))
+    }
+
+    fn var_declaration(&mut self) -> Result<ast::StmtKind> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect variable name.")?
+            .clone();
+
+        let initializer = if self.match_types(&[TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+
+        Ok(ast::StmtKind::Var { name, initializer })
+    }
+
+    fn statement(&mut self) -> Result<ast::Stmt> {
+        let start = self.current;
+        let kind = self.statement_kind()?;
+
+        Ok(self.spanned(start, kind))
+    }
+
+    fn statement_kind(&mut self) -> Result<ast::StmtKind> {
+        if self.match_types(&[TokenType::Continue]) {
+            return self.continue_statement();
+        }
+
+        if self.match_types(&[TokenType::Defer]) {
+            return self.defer_statement();
+        }
+
+        if self.match_types(&[TokenType::For]) {
+            return self.for_statement();
+        }
+
+        if self.match_types(&[TokenType::Global]) {
+            return self.global_statement();
+        }
+
+        if self.match_types(&[TokenType::If]) {
+            return self.if_statement();
+        }
+
+        if self.match_types(&[TokenType::Print]) {
+            return self.print_statement();
+        }
+
+        if self.match_types(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+
+        if self.match_types(&[TokenType::Throw]) {
+            return self.throw_statement();
+        }
+
+        if self.match_types(&[TokenType::Try]) {
+            return self.try_statement();
+        }
+
+        if self.match_types(&[TokenType::While]) {
+            return self.while_statement();
+        }
+
+        if self.match_types(&[TokenType::LeftBrace]) {
+            return Ok(ast::StmtKind::Block(self.block()?));
+        }
+
+        if self.match_types(&[TokenType::Semicolon]) {
+            return Ok(ast::StmtKind::Empty);
+        }
+
+        self.expression_statement()
+    }
+
+    // desugars `for (init; cond; incr) body` into `{ init; while (cond) body }`
+    // with `incr` as the loop's increment, so `continue` still runs it
+    fn for_statement(&mut self) -> Result<ast::StmtKind> {
+        let start = self.current - 1; // the 'for'
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        if self.check(TokenType::Identifier) && self.check_next(TokenType::In) {
+            return self.for_in_statement();
+        }
+
+        let initializer_start = self.current;
+        let initializer = if self.match_types(&[TokenType::Semicolon]) {
+            None
+        } else if self.match_types(&[TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+        let initializer = initializer.map(|kind| self.spanned(initializer_start, kind));
+
+        let condition = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let increment = if self.check(TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let body = ast::StmtKind::While {
+            condition: condition.unwrap_or(ast::Expr::Literal(Literal::Bool(true))),
+            body: Box::new(self.loop_body()?),
+            increment,
+        };
+
+        match initializer {
+            Some(initializer) => {
+                let body = self.spanned(start, body);
+                Ok(ast::StmtKind::Block(vec![initializer, body]))
+            }
+            None => Ok(body),
+        }
+    }
+
+    // `for (name in iterable) body`, after the '('
+    fn for_in_statement(&mut self) -> Result<ast::StmtKind> {
+        let name = self.advance().clone();
+        self.advance(); // the 'in'
+
+        let iterable = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after for-in iterable.")?;
+
+        let body = Box::new(self.loop_body()?);
+
+        Ok(ast::StmtKind::ForIn {
+            name,
+            iterable,
+            body,
+        })
+    }
+
+    fn global_statement(&mut self) -> Result<ast::StmtKind> {
+        let name = self
+            .consume(
+                TokenType::Identifier,
+                "Expect variable name after 'global'.",
+            )?
+            .clone();
+        self.consume(TokenType::Equal, "Expect '=' after global variable name.")?;
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after global assignment.")?;
+
+        Ok(ast::StmtKind::Global { name, value })
+    }
+
+    fn if_statement(&mut self) -> Result<ast::StmtKind> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.statement()?);
+        let else_branch = if self.match_types(&[TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(ast::StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn print_statement(&mut self) -> Result<ast::StmtKind> {
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+
+        Ok(ast::StmtKind::Print(value))
+    }
+
+    fn return_statement(&mut self) -> Result<ast::StmtKind> {
+        let keyword = self.previous().clone();
+
+        if self.function == FunctionKind::None {
+            Err(self.error(&keyword, "Can't return from top-level code."))?;
+        }
+
+        if self.function == FunctionKind::Deferred {
+            Err(self.error(&keyword, "Can't return from a deferred statement."))?;
+        }
+
+        let value = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            if self.function == FunctionKind::Initializer {
+                Err(self.error(&keyword, "Can't return a value from an initializer."))?;
+            }
+
+            Some(self.expression()?)
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+
+        Ok(ast::StmtKind::Return(value))
+    }
+
+    fn throw_statement(&mut self) -> Result<ast::StmtKind> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after thrown value.")?;
+
+        Ok(ast::StmtKind::Throw { keyword, value })
+    }
+
+    fn try_statement(&mut self) -> Result<ast::StmtKind> {
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'.")?;
+        let body = self.block()?;
+
+        let catch = if self.match_types(&[TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after 'catch'.")?;
+            let name = self
+                .consume(TokenType::Identifier, "Expect caught variable name.")?
+                .clone();
+            self.consume(TokenType::RightParen, "Expect ')' after caught variable.")?;
+
+            self.consume(TokenType::LeftBrace, "Expect '{' before catch body.")?;
+            Some((name, self.block()?))
+        } else {
+            None
+        };
+
+        let finally = if self.match_types(&[TokenType::Finally]) {
+            self.consume(TokenType::LeftBrace, "Expect '{' after 'finally'.")?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            let token = self.peek().clone();
+            Err(self.error(&token, "Expect 'catch' or 'finally' after try block."))?;
+        }
+
+        Ok(ast::StmtKind::Try {
+            body,
+            catch,
+            finally,
+        })
+    }
+
+    fn while_statement(&mut self) -> Result<ast::StmtKind> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+
+        let body = Box::new(self.loop_body()?);
+
+        Ok(ast::StmtKind::While {
+            condition,
+            body,
+            increment: None,
+        })
+    }
+
+    // the body of a loop, where `continue` is allowed
+    fn loop_body(&mut self) -> Result<ast::Stmt> {
+        self.loops += 1;
+        let body = self.statement();
+        self.loops -= 1;
+
+        body
+    }
+
+    // `defer` takes any statement, like `defer close();` or `defer { ... }`.
+    // It can't `return` or `continue` past the scope it runs at the end of.
+    fn defer_statement(&mut self) -> Result<ast::StmtKind> {
+        let enclosing = (self.function, self.loops);
+        (self.function, self.loops) = (FunctionKind::Deferred, 0);
+
+        let body = self.statement();
+
+        (self.function, self.loops) = enclosing;
+
+        Ok(ast::StmtKind::Defer(Box::new(body?)))
+    }
+
+    fn continue_statement(&mut self) -> Result<ast::StmtKind> {
+        let keyword = self.previous().clone();
+
+        if self.loops == 0 {
+            Err(self.error(&keyword, "Can't use 'continue' outside of a loop."))?;
+        }
+
+        self.consume(TokenType::Semicolon, "Expect ';' after 'continue'.")?;
+
+        Ok(ast::StmtKind::Continue)
+    }
+
+    fn expression_statement(&mut self) -> Result<ast::StmtKind> {
+        let expr = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+
+        Ok(ast::StmtKind::Expression(expr))
+    }
+
+    // parses the statements of a block whose '{' was already consumed
+    fn block(&mut self) -> Result<Vec<ast::Stmt>> {
+        let mut statements = Vec::new();
+
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            statements.extend(self.declaration());
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+
+        Ok(statements)
+    }
+
+    fn expression(&mut self) -> Result<ast::Expr> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<ast::Expr> {
+        let expr = self.or()?;
+
+        if self.match_types(&[TokenType::Equal]) {
+            let equals = self.previous().clone();
+            let value = self.assignment()?;
+
+            match expr {
+                ast::Expr::Variable { name, .. } => {
+                    return Ok(ast::Expr::Assign {
+                        name,
+                        value: Box::new(value),
+                        depth: ast::Depth::default(),
+                    })
+                }
+                ast::Expr::Get { object, name } => {
+                    return Ok(ast::Expr::Set {
+                        object,
+                        name,
+                        value: Box::new(value),
+                    })
+                }
+                _ => Err(self.error(&equals, "Invalid assignment target."))?,
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<ast::Expr> {
+        let mut expr = self.and()?;
+
+        while self.match_types(&[TokenType::Or]) {
+            let operator = self.previous().clone();
+            let right = self.and()?;
+
+            expr = ast::Expr::Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<ast::Expr> {
+        let mut expr = self.equality()?;
+
+        while self.match_types(&[TokenType::And]) {
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+
+            expr = ast::Expr::Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<ast::Expr> {
+        let mut expr = self.comparison()?;
+
+        while self.match_types(&[TokenType::BangEqual, TokenType::EqualEqual]) {
+            let operator = self.previous().clone();
+            let right = self.comparison()?;
+
+            expr = ast::Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<ast::Expr> {
+        let mut expr = self.range()?;
+
+        while self.match_types(&[
+            TokenType::Greater,
+            TokenType::GreaterEqual,
+            TokenType::Less,
+            TokenType::LessEqual,
+            TokenType::In,
+        ]) {
+            let operator = self.previous().clone();
+            let right = self.range()?;
+
+            expr = ast::Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    // `a..b` and `a..=b` don't chain, so `0..1..2` is an error
+    fn range(&mut self) -> Result<ast::Expr> {
+        let expr = self.term()?;
+
+        if self.match_types(&[TokenType::DotDot, TokenType::DotDotEqual]) {
+            let operator = self.previous().clone();
+            let right = self.term()?;
+
+            return Ok(ast::Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<ast::Expr> {
+        let mut expr = self.factor()?;
+
+        while self.match_types(&[TokenType::Minus, TokenType::Plus]) {
+            let operator = self.previous().clone();
+            let right = self.factor()?;
+
+            expr = ast::Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<ast::Expr> {
+        let mut expr = self.unary()?;
+
+        while self.match_types(&[TokenType::Slash, TokenType::Star]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+
+            expr = ast::Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<ast::Expr> {
+        if self.match_types(&[TokenType::Bang, TokenType::Minus]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+
+            return Ok(ast::Expr::Unary {
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        self.power()
+    }
+
+    // binds tighter than unary minus and is right-associative, so
+    // `-2 ** 3 ** 2` is `-(2 ** (3 ** 2))`
+    fn power(&mut self) -> Result<ast::Expr> {
+        let expr = self.call()?;
+
+        if self.match_types(&[TokenType::StarStar]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+
+            return Ok(ast::Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn call(&mut self) -> Result<ast::Expr> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_types(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_types(&[TokenType::Dot]) {
+                let name = self
+                    .consume(TokenType::Identifier, "Expect property name after '.'.")?
+                    .clone();
+
+                expr = ast::Expr::Get {
+                    object: Box::new(expr),
+                    name,
+                };
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: ast::Expr) -> Result<ast::Expr> {
+        let mut arguments = Vec::new();
+
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    Err(self.error(self.peek(), "Can't have more than 255 arguments."))?;
+                }
+
+                arguments.push(self.expression()?);
+
+                if !self.match_types(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self
+            .consume(TokenType::RightParen, "Expect ')' after arguments.")?
+            .clone();
+
+        Ok(ast::Expr::Call {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        })
+    }
+
+    fn primary(&mut self) -> Result<ast::Expr> {
+        if self.match_types(&[
+            TokenType::False,
+            TokenType::True,
+            TokenType::Nil,
+            TokenType::Number,
+            TokenType::String,
+        ]) {
+            return Ok(ast::Expr::Literal(self.previous().literal.clone()));
+        }
+
+        if self.match_types(&[TokenType::Super]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self
+                .consume(TokenType::Identifier, "Expect superclass method name.")?
+                .clone();
+
+            return Ok(ast::Expr::Super {
+                keyword,
+                method,
+                depth: ast::Depth::default(),
+            });
+        }
+
+        if self.match_types(&[TokenType::This]) {
+            return Ok(ast::Expr::This {
+                keyword: self.previous().clone(),
+                depth: ast::Depth::default(),
+            });
+        }
+
+        if self.match_types(&[TokenType::Identifier]) {
+            return Ok(ast::Expr::Variable {
+                name: self.previous().clone(),
+                depth: ast::Depth::default(),
+                receiver: ast::Depth::default(),
+            });
+        }
+
+        if self.match_types(&[TokenType::LeftParen]) {
+            let expr = self.expression()?;
+            self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
+
+            return Ok(ast::Expr::Grouping(Box::new(expr)));
+        }
+
+        Err(self.error(self.peek(), "Expect expression."))?
+    }
+
+    fn match_types(&mut self, types: &[TokenType]) -> bool {
+        if types.iter().any(|&t| self.check(t)) {
+            self.advance();
+            return true;
+        }
+
+        false
+    }
+
+    fn consume(&mut self, r#type: TokenType, message: &str) -> Result<&Token> {
+        if self.check(r#type) {
+            return Ok(self.advance());
+        }
+
+        Err(self.error(self.peek(), message))?
+    }
+
+    fn check(&self, r#type: TokenType) -> bool {
+        !self.is_at_end() && self.peek().r#type == r#type
+    }
+
+    fn check_next(&self, r#type: TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| token.r#type == r#type)
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+
+        self.previous()
+    }
+
+    // discards tokens up to what is probably the start of the next
+    // statement: just past a ';' or at a keyword beginning one
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().r#type == TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().r#type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Throw
+                | TokenType::Try
+                | TokenType::Defer
+                | TokenType::Global => return,
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().r#type == TokenType::Eof
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+
+    // wraps a statement parsed from the tokens from `start` up to the last
+    // one consumed
+    fn spanned(&self, start: usize, kind: ast::StmtKind) -> ast::Stmt {
+        let first = &self.tokens[start];
+
+        ast::Stmt {
+            kind,
+            line: first.line,
+            span: first.span.start..self.previous().span.end,
+        }
+    }
+
+    fn error(&self, token: &Token, message: &str) -> ParserError {
+        let at = match token.r#type {
+            TokenType::Eof => "end".to_string(),
+            _ => format!("'{}'", token.lexeme),
+        };
+
+        ParserError::Syntax {
+            line: token.line,
+            at,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::Scanner;
+
+    // the parsed statements, printed one per line
+    fn parse(source: &str) -> Result<String> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens()?;
+        let statements = Parser::new(tokens).parse()?;
+        let printed: Vec<String> = statements.iter().map(|stmt| stmt.to_string()).collect();
+
+        Ok(printed.join("\n"))
+    }
+
+    #[test]
+    fn prints_string_literals_quoted() {
+        assert_eq!(parse("print \"1\";").unwrap(), "(print \"1\")");
+        assert_eq!(parse("print 1;").unwrap(), "(print 1)");
+        assert_eq!(
+            parse("print \"a\" + \"b\\n\";").unwrap(),
+            "(print (+ \"a\" \"b\\n\"))"
+        );
+    }
+
+    #[test]
+    fn reports_every_syntax_error_in_order() {
+        let source = "print 1;\nvar = 2;\nprint (3;\nprint 4;\nfun () {}\n";
+
+        assert_eq!(
+            parse(source).unwrap_err().to_string(),
+            "[line 2] Error at '=': Expect variable name.\n\
+             [line 3] Error at ';': Expect ')' after expression.\n\
+             [line 5] Error at '(': Expect function name."
+        );
+    }
+}
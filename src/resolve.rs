@@ -0,0 +1,737 @@
+use crate::diagnostics::{self, Level, Lint, Lints};
+use crate::interp::native::NATIVES;
+use crate::parse::ast::{Depth, Expr, Function, Stmt, StmtKind};
+use crate::scan::token::{Literal, Token, TokenType};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::rc::Rc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+enum ResolverError {
+    #[error("[line {line}] Error at '{lexeme}': {message}")]
+    Resolve {
+        line: u32,
+        lexeme: String,
+        message: String,
+    },
+
+    #[error("{}", .0.join("\n"))]
+    Errors(Vec<String>),
+}
+
+// the kind of class whose methods are being resolved, deciding where `this`
+// and `super` are allowed
+#[derive(Clone, Copy, PartialEq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
+}
+
+// Runs between parsing and interpreting. Every local variable use is
+// annotated with how many scopes out its declaration is, so a closure keeps
+// seeing the variables that were in scope where it was written. The scopes
+// mirror the environments the interpreter creates.
+pub struct Resolver {
+    // local scopes, innermost last. A name maps to false while its own
+    // initializer is being resolved.
+    scopes: Vec<HashMap<Rc<str>, bool>>,
+    class: ClassKind,
+    // the fewest and most arguments each top-level function takes, or None
+    // once its name is bound to anything else as well
+    functions: HashMap<Rc<str>, Option<(usize, usize)>>,
+    // calls of globals by name and their argument counts, checked against
+    // `functions` once every declaration has been seen
+    calls: Vec<(Token, usize)>,
+    errors: Vec<String>,
+    max_errors: usize, // how many of `errors` are reported
+    lints: Lints,
+    warnings: Vec<String>, // lints found at the warning level
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver {
+            scopes: Vec::new(),
+            class: ClassKind::None,
+            functions: HashMap::new(),
+            calls: Vec::new(),
+            errors: Vec::new(),
+            max_errors: diagnostics::DEFAULT_MAX_ERRORS,
+            lints: Lints::default(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    pub fn with_lints(mut self, lints: Lints) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    // the warnings found so far, which don't stop the script from running
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    // reports every error found, not just the first
+    pub fn resolve(&mut self, statements: &[Stmt]) -> Result<()> {
+        self.statements(statements);
+        self.finish()
+    }
+
+    pub fn resolve_expression(&mut self, expr: &Expr) -> Result<()> {
+        self.expression(expr);
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.check_arities();
+
+        if !self.errors.is_empty() {
+            let errors = std::mem::take(&mut self.errors);
+            Err(ResolverError::Errors(diagnostics::cap(
+                errors,
+                self.max_errors,
+            )))?;
+        }
+
+        Ok(())
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Block(statements) => {
+                if statements.is_empty() {
+                    self.lint(Lint::EmptyBlock, stmt.line, "Empty block.");
+                }
+
+                self.begin_scope();
+                self.statements(statements);
+                self.end_scope();
+            }
+            StmtKind::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                self.declare(name);
+                self.define(name);
+                self.bind_global(name, None);
+
+                let enclosing = self.class;
+                self.class = ClassKind::Class;
+
+                // methods are bound through a scope defining `this`, nested in
+                // one defining `super` for subclasses
+                if let Some(superclass) = superclass {
+                    self.class = ClassKind::Subclass;
+                    self.expression(superclass);
+
+                    self.begin_scope();
+                    self.define_name("super");
+                }
+
+                self.begin_scope();
+                self.define_name("this");
+
+                for method in methods {
+                    self.function(method);
+                }
+
+                self.end_scope();
+
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.class = enclosing;
+            }
+            StmtKind::Continue | StmtKind::Empty => {}
+            StmtKind::Defer(body) => self.statement(body),
+            StmtKind::Expression(expr) | StmtKind::Print(expr) => self.expression(expr),
+            StmtKind::ForIn {
+                name,
+                iterable,
+                body,
+            } => {
+                self.expression(iterable);
+
+                self.begin_scope();
+                self.declare(name);
+                self.define(name);
+                self.body(body);
+                self.end_scope();
+            }
+            StmtKind::Function(function) => {
+                self.declare(&function.name);
+                self.define(&function.name);
+                let arity = (function.required(), function.params.len());
+                self.bind_global(&function.name, Some(arity));
+                self.function(function);
+            }
+            StmtKind::Global { name, value } => {
+                self.shadowed_native(name);
+                self.expression(value);
+                self.functions.insert(name.lexeme.clone(), None);
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.condition(condition);
+                self.body(then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.body(else_branch);
+                }
+            }
+            StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            StmtKind::Throw { value, .. } => self.expression(value),
+            StmtKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.begin_scope();
+                self.statements(body);
+                self.end_scope();
+
+                if let Some((name, catch)) = catch {
+                    self.begin_scope();
+                    self.declare(name);
+                    self.define(name);
+                    self.statements(catch);
+                    self.end_scope();
+                }
+
+                if let Some(finally) = finally {
+                    self.begin_scope();
+                    self.statements(finally);
+                    self.end_scope();
+                }
+            }
+            StmtKind::Var { name, initializer } => {
+                self.declare(name);
+                self.bind_global(name, None);
+
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+
+                self.define(name);
+            }
+            StmtKind::While {
+                condition,
+                body,
+                increment,
+            } => {
+                self.condition(condition);
+                self.body(body);
+
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                }
+            }
+        }
+    }
+
+    // An assignment directly in a condition is usually a mistyped `==`.
+    // Wrapping it in another pair of parentheses says it's meant.
+    fn condition(&mut self, condition: &Expr) {
+        if let Expr::Assign { name, .. } = condition {
+            self.lint(
+                Lint::AssignInCondition,
+                name.line,
+                "Assignment used as a condition, wrap it in parentheses if it's intended.",
+            );
+        }
+
+        self.expression(condition);
+    }
+
+    // the parameters and body share the scope the call creates. A default
+    // value can use the parameters before its own.
+    fn function(&mut self, function: &Function) {
+        self.begin_scope();
+
+        for (i, param) in function.params.iter().enumerate() {
+            self.declare(param);
+
+            if let Some(default) = i.checked_sub(function.required()) {
+                self.expression(&function.defaults[default]);
+            }
+
+            self.define(param);
+        }
+
+        self.statements(&function.body);
+        self.end_scope();
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign { name, value, depth } => {
+                self.expression(value);
+                self.resolve_local(name, depth);
+
+                if depth.get().is_none() {
+                    self.functions.insert(name.lexeme.clone(), None);
+                }
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                self.float_equality(left, operator, right);
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::Logical { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.expression(callee);
+
+                for argument in arguments {
+                    self.expression(argument);
+                }
+
+                if let Expr::Variable { name, depth, .. } = &**callee {
+                    if depth.get().is_none() {
+                        self.calls.push((name.clone(), arguments.len()));
+                    }
+                }
+            }
+            Expr::Get { object, .. } => self.expression(object),
+            Expr::Grouping(expr) => self.expression(expr),
+            Expr::Literal(_) => {}
+            Expr::Set { object, value, .. } => {
+                self.expression(value);
+                self.expression(object);
+            }
+            Expr::Super { keyword, depth, .. } => match self.class {
+                ClassKind::None => self.error(keyword, "Can't use 'super' outside of a class."),
+                ClassKind::Class => {
+                    self.error(keyword, "Can't use 'super' in a class with no superclass.")
+                }
+                ClassKind::Subclass => self.resolve_local(keyword, depth),
+            },
+            Expr::This { keyword, depth } => match self.class {
+                ClassKind::None => self.error(keyword, "Can't use 'this' outside of a class."),
+                _ => self.resolve_local(keyword, depth),
+            },
+            Expr::Unary { right, .. } => self.expression(right),
+            Expr::Variable {
+                name,
+                depth,
+                receiver,
+            } => {
+                let scope = self.scopes.last();
+
+                if scope.and_then(|scope| scope.get(&name.lexeme)) == Some(&false) {
+                    self.error(name, "Can't read local variable in its own initializer.");
+                }
+
+                self.resolve_local(name, depth);
+
+                // a name that isn't a local may still be a global, which
+                // wins, so the instance is only tried at runtime
+                if depth.get().is_none() && self.class != ClassKind::None {
+                    receiver.set(self.find_local("this"));
+                }
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    // globals may be redeclared, locals may not
+    fn declare(&mut self, name: &Token) {
+        self.shadowed_native(name);
+
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.insert(name.lexeme.clone(), false).is_some() {
+            self.error(name, "Already a variable with this name in this scope.");
+        }
+    }
+
+    // a later call of the native would get the new binding instead
+    fn shadowed_native(&mut self, name: &Token) {
+        if NATIVES.iter().any(|&(native, ..)| native == &*name.lexeme) {
+            self.lint(
+                Lint::ShadowedNative,
+                name.line,
+                &format!("'{}' shadows the native function.", name.lexeme),
+            );
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.into(), true);
+        }
+    }
+
+    // records what a top-level declaration binds its name to. Only a name
+    // bound once, to a function, has a known arity.
+    fn bind_global(&mut self, name: &Token, arity: Option<(usize, usize)>) {
+        if !self.scopes.is_empty() {
+            return;
+        }
+
+        self.functions
+            .entry(name.lexeme.clone())
+            .and_modify(|known| *known = None)
+            .or_insert(arity);
+    }
+
+    // a call of a function whose declaration is known can be checked now;
+    // anything else is checked when it runs
+    fn check_arities(&mut self) {
+        for (name, count) in std::mem::take(&mut self.calls) {
+            let Some(&Some((min, max))) = self.functions.get(&name.lexeme) else {
+                continue;
+            };
+
+            if count < min || count > max {
+                let message = match min == max {
+                    true => format!("Expected {} arguments but got {}.", max, count),
+                    false => format!("Expected {} to {} arguments but got {}.", min, max, count),
+                };
+                self.error(&name, &message);
+            }
+        }
+    }
+
+    // a name not found in any local scope is left as a global
+    fn resolve_local(&mut self, name: &Token, depth: &Depth) {
+        depth.set(self.find_local(&name.lexeme));
+    }
+
+    // how many scopes out `name` is declared, if it's a local
+    fn find_local(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name))
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        let error = ResolverError::Resolve {
+            line: token.line,
+            lexeme: token.lexeme.to_string(),
+            message: message.to_string(),
+        };
+
+        self.errors.push(error.to_string());
+    }
+
+    // A lone `;` as the body of a loop or branch is usually a stray one, as
+    // in `while (c);`. An empty `{}` body is written on purpose.
+    fn body(&mut self, body: &Stmt) {
+        if let StmtKind::Empty = body.kind {
+            self.lint(
+                Lint::EmptyBody,
+                body.line,
+                "Empty statement as a body, use '{}' if it's intended.",
+            );
+        }
+
+        self.statement(body);
+    }
+
+    // Comparing numbers that likely have a fractional part with `==` is
+    // error-prone, `0.1 + 0.2 == 0.3` is false. Comparing to a whole number
+    // is left alone, the check can't tell what a variable holds.
+    fn float_equality(&mut self, left: &Expr, operator: &Token, right: &Expr) {
+        if !matches!(
+            operator.r#type,
+            TokenType::EqualEqual | TokenType::BangEqual
+        ) || !(fractional(left) || fractional(right))
+            || integer_literal(left)
+            || integer_literal(right)
+        {
+            return;
+        }
+
+        self.lint(
+            Lint::FloatEquality,
+            operator.line,
+            &format!(
+                "Floating point numbers compared with '{}', use approx_eq(a, b, epsilon) instead.",
+                operator.lexeme
+            ),
+        );
+    }
+
+    fn lint(&mut self, lint: Lint, line: u32, message: &str) {
+        match self.lints.report(lint, line, message) {
+            Some((Level::Deny, report)) => self.errors.push(report),
+            Some((_, report)) => self.warnings.push(report),
+            None => {}
+        }
+    }
+}
+
+// whether a numeric expression likely has a fractional part: it divides, or
+// does arithmetic on a number literal with one
+fn fractional(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(Literal::Number(n)) => n.fract() != 0.0,
+        Expr::Grouping(expr) => fractional(expr),
+        Expr::Unary { operator, right } => operator.r#type == TokenType::Minus && fractional(right),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => match operator.r#type {
+            TokenType::Slash => true,
+            TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::StarStar => {
+                fractional(left) || fractional(right)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn integer_literal(expr: &Expr) -> bool {
+    matches!(expr, Expr::Literal(Literal::Number(n)) if n.fract() == 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::scan::Scanner;
+
+    fn resolve(source: &str) -> Result<()> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens()?;
+        let statements = Parser::new(tokens).parse()?;
+
+        Resolver::new().resolve(&statements)
+    }
+
+    // the warnings from resolving source that has no errors
+    fn warnings(source: &str) -> Vec<String> {
+        warnings_with(source, Lints::default())
+    }
+
+    fn warnings_with(source: &str, lints: Lints) -> Vec<String> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let mut resolver = Resolver::new().with_lints(lints);
+        resolver.resolve(&statements).unwrap();
+
+        resolver.take_warnings()
+    }
+
+    #[test]
+    fn rejects_reading_a_local_in_its_own_initializer() {
+        let error = resolve("var a = 1; { var a = a + 1; }").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at 'a': Can't read local variable in its own initializer."
+        );
+    }
+
+    #[test]
+    fn rejects_redeclaring_a_local() {
+        let error = resolve("{ var b = 1; var b = 2; }").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at 'b': Already a variable with this name in this scope."
+        );
+    }
+
+    #[test]
+    fn rejects_this_outside_of_a_class() {
+        let error = resolve("print this; fun f() { return this; }").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at 'this': Can't use 'this' outside of a class.\n\
+             [line 1] Error at 'this': Can't use 'this' outside of a class."
+        );
+    }
+
+    #[test]
+    fn rejects_super_without_a_superclass() {
+        let error = resolve("class A { m() { super.m(); } } super.m();").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at 'super': Can't use 'super' in a class with no superclass.\n\
+             [line 1] Error at 'super': Can't use 'super' outside of a class."
+        );
+    }
+
+    #[test]
+    fn rejects_calling_a_known_function_with_the_wrong_arity() {
+        let error = resolve("fun add(a, b) { return a + b; }\nprint add(1);").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "[line 2] Error at 'add': Expected 2 arguments but got 1."
+        );
+    }
+
+    #[test]
+    fn checks_known_calls_against_parameter_defaults() {
+        assert!(resolve("fun f(a, b = 1) {} f(1); f(1, 2);").is_ok());
+
+        let error = resolve("fun f(a, b = 1) {} f();").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at 'f': Expected 1 to 2 arguments but got 0."
+        );
+    }
+
+    #[test]
+    fn leaves_rebound_and_local_functions_to_runtime() {
+        assert!(resolve("fun f(a) {} f = clock; f();").is_ok());
+        assert!(resolve("fun f(a) {} var f; f();").is_ok());
+        assert!(resolve("fun f(a) {} { fun f() {} f(); }").is_ok());
+    }
+
+    #[test]
+    fn allows_redeclaring_a_global() {
+        assert!(resolve("var a = 1; var a = a + 1;").is_ok());
+    }
+
+    #[test]
+    fn warns_about_assignment_as_a_condition() {
+        assert_eq!(
+            warnings("var a; var b; if (a = b) print a;"),
+            ["[line 1] Warning: Assignment used as a condition, wrap it in parentheses if it's intended. [assign-in-condition]"]
+        );
+        assert_eq!(warnings("var a; while (a = false) {}").len(), 1);
+        assert_eq!(warnings("var a; for (; a = false;) {}").len(), 1);
+    }
+
+    #[test]
+    fn parenthesized_assignment_as_a_condition_is_intended() {
+        assert!(warnings("var a; var b; if ((a = b)) print a;").is_empty());
+        assert!(warnings("var a; var b; if (a == b) print a;").is_empty());
+    }
+
+    #[test]
+    fn denied_lints_are_errors() {
+        let tokens = Scanner::new("var a; if (a = 1) {}".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let mut lints = Lints::default();
+        lints.set(Lint::AssignInCondition, Level::Deny);
+
+        let error = Resolver::new()
+            .with_lints(lints)
+            .resolve(&statements)
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error: Assignment used as a condition, wrap it in parentheses if it's intended. [assign-in-condition]"
+        );
+    }
+
+    #[test]
+    fn warns_about_an_empty_statement_as_a_body() {
+        assert_eq!(
+            warnings("var c = false;\nwhile (c);"),
+            ["[line 2] Warning: Empty statement as a body, use '{}' if it's intended. [empty-body]"]
+        );
+        assert_eq!(warnings("for (var i = 0; i < 3; i = i + 1);").len(), 1);
+        assert_eq!(warnings("if (true); else;").len(), 2);
+    }
+
+    #[test]
+    fn empty_blocks_are_only_reported_when_asked_for() {
+        let source = "var c = false; while (c) {}";
+        assert!(warnings(source).is_empty());
+
+        let mut lints = Lints::default();
+        lints.set(Lint::EmptyBlock, Level::Warn);
+
+        assert_eq!(
+            warnings_with(source, lints),
+            ["[line 1] Warning: Empty block. [empty-block]"]
+        );
+    }
+
+    #[test]
+    fn warns_about_shadowing_a_native() {
+        assert_eq!(
+            warnings("var clock = 1;"),
+            ["[line 1] Warning: 'clock' shadows the native function. [shadowed-native]"]
+        );
+        assert_eq!(warnings("fun f(clock) { { fun clock() {} } }").len(), 2);
+        assert!(warnings("var clocks = 1; fun tick() {}").is_empty());
+    }
+
+    #[test]
+    fn shadowing_a_native_can_be_allowed() {
+        let mut lints = Lints::default();
+        lints.set(Lint::ShadowedNative, Level::Allow);
+
+        assert!(warnings_with("var clock = 1;", lints).is_empty());
+    }
+
+    #[test]
+    fn float_equality_is_only_reported_when_asked_for() {
+        let source = "var x = 0.3; print x == 0.1 + 0.2;";
+        assert!(warnings(source).is_empty());
+
+        let mut lints = Lints::default();
+        lints.set(Lint::FloatEquality, Level::Warn);
+
+        assert_eq!(
+            warnings_with(source, lints.clone()),
+            ["[line 1] Warning: Floating point numbers compared with '==', use approx_eq(a, b, epsilon) instead. [float-equality]"]
+        );
+        assert_eq!(
+            warnings_with("var x; print x != 1 / 3;", lints.clone()).len(),
+            1
+        );
+        assert!(warnings_with("var x; print x == 1; print x == 2 + 3;", lints.clone()).is_empty());
+        assert!(warnings_with("print 1 == 0.5;", lints).is_empty());
+    }
+}
@@ -1,15 +1,48 @@
 pub mod token;
 
+use crate::diagnostics;
 use anyhow::Result;
+use std::collections::HashSet;
+use std::rc::Rc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 enum ScannerError {
-    #[error("Unexpected character '{0}'")]
-    UnexpectedCharacter(char),
+    #[error("[line {0}] Unexpected character '{1}'")]
+    UnexpectedCharacter(u32, char),
 
-    #[error("Unexpected character")]
-    UnterminatedString,
+    #[error("[line {0}] Unterminated string")]
+    UnterminatedString(u32),
+
+    #[error("[line {0}] Invalid escape sequence '{1}'")]
+    InvalidEscape(u32, String),
+
+    #[error("[line {0}] Invalid number literal '{1}'")]
+    InvalidNumber(u32, String),
+
+    #[error("Source is not valid UTF-8 (invalid byte at offset {0})")]
+    InvalidUtf8(usize),
+
+    #[error("[line {0}] Unterminated block comment")]
+    UnterminatedComment(u32),
+
+    #[error("[line {0}] Missing flag after '#if'")]
+    MissingFlag(u32),
+
+    #[error("[line {0}] Unterminated '#if'")]
+    UnterminatedIf(u32),
+
+    #[error("[line {0}] '#endif' without a matching '#if'")]
+    UnmatchedEndif(u32),
+
+    #[error("{}", .0.join("\n"))]
+    Errors(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
 }
 
 pub struct Scanner {
@@ -18,6 +51,15 @@ pub struct Scanner {
     start: usize,   // points to the first charector of a lexeme
     current: usize, // points to to the current charecter being considered as part of the lexeme
     line: u32,
+    line_start: usize,         // offset of the first character of the current line
+    start_line: u32,           // the line `start` is on
+    start_column: u32,         // the column of `start`, counting characters from 1
+    strings: HashSet<Rc<str>>, // interned lexemes and string literals shared between tokens
+    defines: HashSet<String>,  // flags enabling `//#if FLAG` regions
+    open_ifs: Vec<u32>,        // lines of the enabled `//#if`s awaiting an `//#endif`
+    errors: Vec<String>,       // errors scanned past so far
+    max_errors: usize,         // how many of them are reported
+    offset: usize,             // added to token spans
 }
 
 impl Scanner {
@@ -28,22 +70,97 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 1,
+            strings: HashSet::new(),
+            defines: HashSet::new(),
+            open_ifs: Vec::new(),
+            errors: Vec::new(),
+            max_errors: diagnostics::DEFAULT_MAX_ERRORS,
+            offset: 0,
         }
     }
 
+    pub fn with_defines(mut self, defines: &[String]) -> Self {
+        self.defines.extend(defines.iter().cloned());
+        self
+    }
+
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // shifts token spans, for source that continues text scanned earlier
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn from_bytes(bytes: &[u8], encoding: Encoding) -> Result<Self> {
+        let source = match encoding {
+            Encoding::Utf8 => std::str::from_utf8(bytes)
+                .map_err(|e| ScannerError::InvalidUtf8(e.valid_up_to()))?
+                .to_string(),
+            // every Latin-1 byte maps directly to the code point of the same value
+            Encoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+        };
+
+        Ok(Scanner::new(source))
+    }
+
+    // an error doesn't stop the scan; every error found is reported at the end
     pub fn scan_tokens(&mut self) -> Result<Vec<token::Token>> {
         while !self.is_at_end() {
-            self.start = self.current;
-            self.scan_token()?;
+            self.begin_token();
+
+            if let Err(e) = self.scan_token() {
+                self.errors.push(e.to_string());
+            }
         }
-        self.tokens.push(token::Token {
-            r#type: token::TokenType::Eof,
-            lexeme: "".to_string(),
-            literal: "".to_string(),
-            line: self.line,
-        });
 
-        Ok(self.tokens.clone())
+        for &line in &self.open_ifs {
+            self.errors
+                .push(ScannerError::UnterminatedIf(line).to_string());
+        }
+
+        if !self.errors.is_empty() {
+            let errors = std::mem::take(&mut self.errors);
+            Err(ScannerError::Errors(diagnostics::cap(
+                errors,
+                self.max_errors,
+            )))?;
+        }
+
+        self.begin_token();
+        self.add_token(token::TokenType::Eof);
+
+        Ok(std::mem::take(&mut self.tokens))
+    }
+
+    // starts the next lexeme at `current`. Its column is counted on from the
+    // previous lexeme's when on the same line, so long lines stay linear.
+    fn begin_token(&mut self) {
+        let (offset, column) = if self.start < self.line_start {
+            (self.line_start, 1)
+        } else {
+            (self.start, self.start_column)
+        };
+
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = column + self.source[offset..self.start].chars().count() as u32;
+    }
+
+    // called just after consuming a '\n'
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
     }
 
     fn is_at_end(&self) -> bool {
@@ -59,11 +176,27 @@ impl Scanner {
             '{' => self.add_token(token::TokenType::LeftBrace),
             '}' => self.add_token(token::TokenType::RightBrace),
             ',' => self.add_token(token::TokenType::Comma),
-            '.' => self.add_token(token::TokenType::Dot),
+            '.' => {
+                if self.match_char('.') {
+                    if self.match_char('=') {
+                        self.add_token(token::TokenType::DotDotEqual)
+                    } else {
+                        self.add_token(token::TokenType::DotDot)
+                    }
+                } else {
+                    self.add_token(token::TokenType::Dot)
+                }
+            }
             '-' => self.add_token(token::TokenType::Minus),
             '+' => self.add_token(token::TokenType::Plus),
             ';' => self.add_token(token::TokenType::Semicolon),
-            '*' => self.add_token(token::TokenType::Star),
+            '*' => {
+                if self.match_char('*') {
+                    self.add_token(token::TokenType::StarStar)
+                } else {
+                    self.add_token(token::TokenType::Star)
+                }
+            }
             '!' => {
                 if self.match_char('=') {
                     self.add_token(token::TokenType::BangEqual)
@@ -94,26 +227,128 @@ impl Scanner {
             }
             '/' => {
                 if self.match_char('/') {
-                    while self.peek() != '\n' && !self.is_at_end() {
-                        self.advance();
+                    if self.at_line_start() && self.match_char('#') {
+                        self.directive()?;
+                    } else {
+                        self.skip_line();
                     }
+                } else if self.match_char('*') {
+                    self.block_comment()?;
                 } else {
                     self.add_token(token::TokenType::Slash)
                 }
             }
             ' ' | '\r' | '\t' => {} // ignore whitespace
-            '\n' => self.line += 1,
+            '\n' => self.newline(),
             '"' => self.string()?,
             '0'..='9' => self.number()?,
             'a'..='z' | 'A'..='Z' | '_' => self.identifier()?,
-            _ => Err(ScannerError::UnexpectedCharacter(c))?,
+            _ => Err(ScannerError::UnexpectedCharacter(self.line, c))?,
+        }
+
+        Ok(())
+    }
+
+    fn skip_line(&mut self) {
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advance();
+        }
+    }
+
+    // skips a `/* ... */` comment, which may contain nested ones
+    fn block_comment(&mut self) -> Result<()> {
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                Err(ScannerError::UnterminatedComment(self.start_line))?;
+            }
+
+            match self.advance() {
+                '\n' => self.newline(),
+                '/' if self.match_char('*') => depth += 1,
+                '*' if self.match_char('/') => depth -= 1,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    // whether the current lexeme is the first non-blank text on its line.
+    // Only there is `//#` a directive, the same as in skip_region.
+    fn at_line_start(&self) -> bool {
+        self.source[self.line_start..self.start]
+            .trim_start_matches([' ', '\t'])
+            .is_empty()
+    }
+
+    // handles the rest of a `//#` comment line. `#if FLAG` keeps the region
+    // up to the matching `#endif` only if FLAG was defined; any other
+    // directive is treated as a plain comment.
+    fn directive(&mut self) -> Result<()> {
+        let start = self.current;
+        self.skip_line();
+
+        let mut words = self.source[start..self.current].split_whitespace();
+
+        match words.next() {
+            Some("if") => {
+                let enabled = match words.next() {
+                    Some(flag) => self.defines.contains(flag),
+                    None => Err(ScannerError::MissingFlag(self.line))?,
+                };
+
+                if enabled {
+                    self.open_ifs.push(self.line);
+                } else {
+                    self.skip_region()?;
+                }
+            }
+            Some("endif") => {
+                self.open_ifs
+                    .pop()
+                    .ok_or(ScannerError::UnmatchedEndif(self.line))?;
+            }
+            _ => {}
         }
 
         Ok(())
     }
 
+    // skips whole lines up to and including the `//#endif` closing a
+    // disabled `//#if`, counting the skipped lines
+    fn skip_region(&mut self) -> Result<()> {
+        let line = self.line;
+        let mut depth = 0;
+
+        while !self.is_at_end() {
+            self.advance(); // the '\n' ending the previous line
+            self.newline();
+
+            let text = self.source[self.current..].trim_start_matches([' ', '\t']);
+            let directive = text
+                .strip_prefix("//#")
+                .and_then(|d| d.split_whitespace().next());
+
+            match directive {
+                Some("if") => depth += 1,
+                Some("endif") if depth == 0 => {
+                    self.skip_line();
+                    return Ok(());
+                }
+                Some("endif") => depth -= 1,
+                _ => {}
+            }
+
+            self.skip_line();
+        }
+
+        Err(ScannerError::UnterminatedIf(line))?
+    }
+
     fn identifier(&mut self) -> Result<()> {
-        while self.peek().is_alphanumeric() {
+        while self.peek().is_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
 
@@ -121,92 +356,186 @@ impl Scanner {
 
         match text {
             "and" => self.add_token(token::TokenType::And),
+            "catch" => self.add_token(token::TokenType::Catch),
             "class" => self.add_token(token::TokenType::Class),
+            "continue" => self.add_token(token::TokenType::Continue),
+            "defer" => self.add_token(token::TokenType::Defer),
             "else" => self.add_token(token::TokenType::Else),
-            "false" => self.add_token(token::TokenType::False),
+            "false" => self.add_token_literal(token::TokenType::False, token::Literal::Bool(false)),
+            "finally" => self.add_token(token::TokenType::Finally),
             "for" => self.add_token(token::TokenType::For),
             "fun" => self.add_token(token::TokenType::Fun),
+            "global" => self.add_token(token::TokenType::Global),
             "if" => self.add_token(token::TokenType::If),
-            "nil" => self.add_token(token::TokenType::Nil),
+            "in" => self.add_token(token::TokenType::In),
+            "nil" => self.add_token_literal(token::TokenType::Nil, token::Literal::Nil),
             "or" => self.add_token(token::TokenType::Or),
             "print" => self.add_token(token::TokenType::Print),
             "return" => self.add_token(token::TokenType::Return),
             "super" => self.add_token(token::TokenType::Super),
             "this" => self.add_token(token::TokenType::This),
-            "true" => self.add_token(token::TokenType::True),
+            "throw" => self.add_token(token::TokenType::Throw),
+            "true" => self.add_token_literal(token::TokenType::True, token::Literal::Bool(true)),
+            "try" => self.add_token(token::TokenType::Try),
             "var" => self.add_token(token::TokenType::Var),
             "while" => self.add_token(token::TokenType::While),
-            _ => self.add_token_literal(token::TokenType::Identifier, text.to_string()),
+            _ => self.add_token(token::TokenType::Identifier),
         }
 
         Ok(())
     }
 
     fn number(&mut self) -> Result<()> {
-        while self.peek().is_digit(10) {
-            self.advance();
+        let radix = match (&self.source[self.start..self.current], self.peek()) {
+            ("0", 'x' | 'X') => 16,
+            ("0", 'o' | 'O') => 8,
+            ("0", 'b' | 'B') => 2,
+            _ => 10,
+        };
+
+        if radix != 10 {
+            self.advance(); // consume the base prefix
+
+            // the prefix must be followed by a digit, not a separator
+            if !self.peek().is_digit(radix) {
+                Err(self.invalid_number())?;
+            }
+
+            self.digits(radix)?;
+
+            if self.peek().is_alphanumeric() {
+                Err(self.invalid_number())?;
+            }
+
+            let digits = self.source[self.start + 2..self.current].replace('_', "");
+            let value = u64::from_str_radix(&digits, radix)
+                .map_err(|_| ScannerError::InvalidNumber(self.line, self.lexeme()))?;
+
+            self.add_token_literal(
+                token::TokenType::Number,
+                token::Literal::Number(value as f64),
+            );
+
+            return Ok(());
         }
 
+        self.digits(10)?;
+
         // Look for a fractional part.
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             self.advance(); //consume the '.'
 
-            while self.peek().is_digit(10) {
-                self.advance();
-            }
+            self.digits(10)?;
         }
 
-        self.add_token_literal(
-            token::TokenType::Number,
-            self.source[self.start..self.current].to_string(),
-        );
+        let value = self
+            .lexeme()
+            .replace('_', "")
+            .parse()
+            .map_err(|_| ScannerError::InvalidNumber(self.line, self.lexeme()))?;
+
+        self.add_token_literal(token::TokenType::Number, token::Literal::Number(value));
 
         Ok(())
     }
 
+    // includes the offending character in the reported lexeme
+    fn invalid_number(&mut self) -> ScannerError {
+        if !self.is_at_end() {
+            self.advance();
+        }
+
+        ScannerError::InvalidNumber(self.line, self.lexeme())
+    }
+
+    // consumes digits of the given radix, allowing single '_' separators
+    // between them
+    fn digits(&mut self, radix: u32) -> Result<()> {
+        loop {
+            if self.peek().is_digit(radix) {
+                self.advance();
+            } else if self.peek() == '_' {
+                self.advance();
+
+                if !self.peek().is_digit(radix) {
+                    Err(self.invalid_number())?;
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    // escapes are replaced in the literal value while the lexeme keeps them
+    // as written. An invalid escape is reported once the whole string has
+    // been consumed.
     fn string(&mut self) -> Result<()> {
+        let mut value = String::new();
+        let mut invalid = None;
+
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
-            }
+            match self.advance() {
+                '\\' if !self.is_at_end() => {
+                    let escaped = self.advance();
+
+                    match escaped {
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        'r' => value.push('\r'),
+                        '0' => value.push('\0'),
+                        '\\' | '"' => value.push(escaped),
+                        _ => {
+                            invalid.get_or_insert(ScannerError::InvalidEscape(
+                                self.line,
+                                format!("\\{}", escaped),
+                            ));
+                        }
+                    }
 
-            self.advance();
+                    if escaped == '\n' {
+                        self.newline();
+                    }
+                }
+                '\n' => {
+                    value.push('\n');
+                    self.newline();
+                }
+                c => value.push(c),
+            }
         }
 
         if self.is_at_end() {
-            Err(ScannerError::UnterminatedString)?;
+            Err(ScannerError::UnterminatedString(self.start_line))?;
         }
 
         // The closing ".
         self.advance();
 
-        self.add_token_literal(
-            token::TokenType::String,
-            self.source[self.start + 1..self.current - 1].to_string(),
-        );
+        if let Some(invalid) = invalid {
+            Err(invalid)?;
+        }
+
+        let value = intern(&mut self.strings, &value);
+
+        self.add_token_literal(token::TokenType::String, token::Literal::String(value));
 
         Ok(())
     }
 
+    // `current` is a byte offset, so characters are read from the remaining
+    // slice rather than by char index
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-
-        return self.source.chars().nth(self.current + 1).unwrap();
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-
-        self.source.chars().nth(self.current).unwrap()
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn advance(&mut self) -> char {
-        self.current += 1;
-        self.source.chars().nth(self.current - 1).unwrap()
+        let c = self.peek();
+        self.current += c.len_utf8();
+        c
     }
 
     fn match_char(&mut self, expected: char) -> bool {
@@ -214,24 +543,229 @@ impl Scanner {
             return false;
         }
 
-        if self.source.chars().nth(self.current) != Some(expected) {
+        if self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
-        return true;
+        self.current += expected.len_utf8();
+        true
+    }
+
+    fn lexeme(&self) -> String {
+        self.source[self.start..self.current].to_string()
     }
 
     fn add_token(&mut self, r#type: token::TokenType) {
-        self.add_token_literal(r#type, "".to_string())
+        self.add_token_literal(r#type, token::Literal::None)
     }
 
-    fn add_token_literal(&mut self, r#type: token::TokenType, literal: String) {
+    fn add_token_literal(&mut self, r#type: token::TokenType, literal: token::Literal) {
+        let lexeme = intern(&mut self.strings, &self.source[self.start..self.current]);
+
         self.tokens.push(token::Token {
             r#type,
-            lexeme: self.source[self.start..self.current].to_string(),
+            lexeme,
             literal,
-            line: self.line,
+            line: self.start_line,
+            column: self.start_column,
+            span: self.offset + self.start..self.offset + self.current,
         });
     }
 }
+
+// repeated identifiers, keywords and operators share one allocation
+fn intern(strings: &mut HashSet<Rc<str>>, s: &str) -> Rc<str> {
+    if let Some(interned) = strings.get(s) {
+        return interned.clone();
+    }
+
+    let interned: Rc<str> = Rc::from(s);
+    strings.insert(interned.clone());
+
+    interned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token::Literal;
+
+    fn scan(source: &str) -> Result<Vec<token::Token>> {
+        Scanner::new(source.to_string()).scan_tokens()
+    }
+
+    fn number(source: &str) -> f64 {
+        match scan(source).unwrap()[0].literal {
+            Literal::Number(n) => n,
+            ref literal => panic!("{source} scanned to {literal:?}"),
+        }
+    }
+
+    #[test]
+    fn scans_numbers_with_prefixes_and_separators() {
+        assert_eq!(number("0xFF_FF"), 65535.0);
+        assert_eq!(number("0b1010_1010"), 170.0);
+        assert_eq!(number("0o7_7"), 63.0);
+        assert_eq!(number("1_000.5"), 1000.5);
+        assert_eq!(number("0xFFFFFFFFFFFFFFFF"), u64::MAX as f64);
+    }
+
+    #[test]
+    fn rejects_malformed_numbers() {
+        for (source, lexeme) in [
+            ("0x_FF", "0x_"),
+            ("0xFF_", "0xFF_"),
+            ("1__0", "1__"),
+            ("0b2", "0b2"),
+            ("0x1FFFFFFFFFFFFFFFF", "0x1FFFFFFFFFFFFFFFF"),
+        ] {
+            let error = scan(source).unwrap_err();
+
+            assert_eq!(
+                error.to_string(),
+                format!("[line 1] Invalid number literal '{lexeme}'"),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_latin1_source() {
+        let tokens = Scanner::from_bytes(b"\"caf\xe9\"", Encoding::Latin1)
+            .unwrap()
+            .scan_tokens()
+            .unwrap();
+
+        assert_eq!(tokens[0].literal, Literal::String("café".into()));
+    }
+
+    #[test]
+    fn rejects_invalid_utf8_source() {
+        let error = Scanner::from_bytes(b"\"caf\xe9\"", Encoding::Utf8)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "Source is not valid UTF-8 (invalid byte at offset 4)"
+        );
+    }
+
+    #[test]
+    fn tokens_compare_by_value_and_share_interned_text() {
+        let source = "var name = \"x\"; print name + \"x\";";
+        let first = scan(source).unwrap();
+
+        assert_eq!(first, scan(source).unwrap());
+
+        // both `name`s and both "x" literals point at the same text
+        assert!(Rc::ptr_eq(&first[1].lexeme, &first[6].lexeme));
+
+        match (&first[3].literal, &first[8].literal) {
+            (Literal::String(a), Literal::String(b)) => assert!(Rc::ptr_eq(a, b)),
+            literals => panic!("expected two strings, got {literals:?}"),
+        }
+    }
+
+    #[test]
+    fn includes_if_regions_only_when_the_flag_is_defined() {
+        let source = "//#if DEBUG\nprint \"debug\";\n//#endif\nprint 1;";
+        let scan_with = |defines: &[String]| {
+            Scanner::new(source.to_string())
+                .with_defines(defines)
+                .scan_tokens()
+                .unwrap()
+        };
+
+        let with = scan_with(&["DEBUG".to_string()]);
+        let without = scan_with(&[]);
+
+        assert_eq!(with.len(), 7);
+        assert_eq!(with[1].literal, Literal::String("debug".into()));
+
+        // the skipped lines still count towards later tokens' lines
+        assert_eq!(without.len(), 4);
+        assert_eq!((without[0].line, without[1].line), (4, 4));
+    }
+
+    #[test]
+    fn directives_after_code_are_plain_comments() {
+        let scan_with = |source: &str, defines: &[String]| {
+            Scanner::new(source.to_string())
+                .with_defines(defines)
+                .scan_tokens()
+        };
+        let a = ["A".to_string()];
+
+        // the `//#if B` doesn't open a region, so the first `//#endif` closes A
+        let source = "//#if A\nprint 1; //#if B\n//#endif\nprint 2;";
+        assert_eq!(scan_with(source, &a).unwrap().len(), 7);
+        assert_eq!(scan_with(source, &[]).unwrap().len(), 4);
+
+        let source = "//#if A\nprint 1; //#if B\n//#endif\nprint 2;\n//#endif";
+        for defines in [&a[..], &[]] {
+            assert_eq!(
+                scan_with(source, defines).unwrap_err().to_string(),
+                "[line 5] '#endif' without a matching '#if'"
+            );
+        }
+    }
+
+    #[test]
+    fn identifiers_can_contain_underscores() {
+        let tokens = scan("approx_eq _private x_1").unwrap();
+        let lexemes: Vec<&str> = tokens.iter().map(|token| &*token.lexeme).collect();
+
+        assert_eq!(lexemes, ["approx_eq", "_private", "x_1", ""]);
+    }
+
+    #[test]
+    fn reports_every_scan_error_together() {
+        assert_eq!(
+            scan("@ # \"a\\q\"\nprint 1;\n$").unwrap_err().to_string(),
+            "[line 1] Unexpected character '@'\n\
+             [line 1] Unexpected character '#'\n\
+             [line 1] Invalid escape sequence '\\q'\n\
+             [line 3] Unexpected character '$'"
+        );
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        let tokens = scan("1 /* outer /* inner */ still outer */ 2").unwrap();
+        let lexemes: Vec<&str> = tokens.iter().map(|token| &*token.lexeme).collect();
+
+        assert_eq!(lexemes, ["1", "2", ""]);
+    }
+
+    #[test]
+    fn block_comments_count_lines() {
+        let tokens = scan("/* one\ntwo /* three\n*/ four\n*/ x").unwrap();
+
+        assert_eq!(tokens[0].lexeme.as_ref(), "x");
+        assert_eq!(tokens[0].line, 4);
+    }
+
+    #[test]
+    fn rejects_unterminated_block_comments() {
+        assert_eq!(
+            scan("print 1;\n/* a /* b */\n").unwrap_err().to_string(),
+            "[line 2] Unterminated block comment"
+        );
+    }
+
+    #[test]
+    fn decodes_escape_sequences() {
+        let tokens = scan(r#""a\n\t\\\"\r\0b""#).unwrap();
+
+        assert_eq!(tokens[0].literal, Literal::String("a\n\t\\\"\r\0b".into()));
+    }
+
+    #[test]
+    fn rejects_invalid_escapes() {
+        assert_eq!(
+            scan("print 1;\n\"a\\qb\";").unwrap_err().to_string(),
+            "[line 2] Invalid escape sequence '\\q'"
+        );
+    }
+}
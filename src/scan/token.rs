@@ -1,6 +1,8 @@
 use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenType {
     // Single-character tokens.
     LeftParen,
@@ -24,6 +26,9 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    StarStar,
+    DotDot,
+    DotDotEqual,
 
     // Literals.
     Identifier,
@@ -32,39 +37,158 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Catch,
     Class,
+    Continue,
+    Defer,
     Else,
     False,
+    Finally,
     Fun,
     For,
+    Global,
     If,
+    In,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
     Eof,
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for TokenType {
+    // Prints the variant name in SCREAMING_SNAKE_CASE, e.g. `BANG_EQUAL`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, c) in format!("{:?}", self).chars().enumerate() {
+            if i > 0 && c.is_ascii_uppercase() {
+                write!(f, "_")?;
+            }
+            write!(f, "{}", c.to_ascii_uppercase())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Number(f64),
+    String(Rc<str>),
+    Bool(bool),
+    Nil,
+    None, // the token carries no literal value
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Number(n) => write!(f, "{}", n),
+            Literal::String(s) => write!(f, "{}", s),
+            Literal::Bool(b) => write!(f, "{}", b),
+            Literal::Nil => write!(f, "nil"),
+            Literal::None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub r#type: TokenType,
-    pub lexeme: String,
-    pub literal: String,
-    pub line: u32,
+    pub lexeme: Rc<str>,
+    pub literal: Literal,
+    pub line: u32,          // where the token starts
+    pub column: u32,        // of the first character, counting characters from 1
+    pub span: Range<usize>, // byte offsets of the lexeme in the source
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "line: {}, type: {:?}, lexeme: {}, literal: {}",
-            self.line, self.r#type, self.lexeme, self.literal
+        write!(f, "[line {}:{}] {}", self.line, self.column, self.r#type)?;
+
+        if !self.lexeme.is_empty() {
+            write!(f, " {}", self.lexeme)?;
+        }
+
+        // keywords and operators only show their lexeme
+        match (&self.literal, self.r#type) {
+            (Literal::Number(_) | Literal::String(_), _) => write!(f, " (={})", self.literal)?,
+            (_, TokenType::Identifier) => write!(f, " (={})", self.lexeme)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl Token {
+    // the byte span comes last so the leading columns stay stable
+    pub const CSV_HEADER: &'static str = "line,column,type,lexeme,literal,start,end";
+
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{}",
+            self.line,
+            self.column,
+            self.r#type,
+            csv_field(&self.lexeme),
+            csv_field(&self.literal.to_string()),
+            self.span.start,
+            self.span.end
         )
     }
 }
+
+// quotes a field containing separators, quotes or line breaks, doubling any
+// embedded quotes
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scan::Scanner;
+
+    fn display(source: &str) -> String {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+
+        tokens[0].to_string()
+    }
+
+    #[test]
+    fn displays_the_literal_of_numbers_and_strings() {
+        assert_eq!(display("3.14"), "[line 1:1] NUMBER 3.14 (=3.14)");
+        assert_eq!(
+            display("\"hi\\tthere\""),
+            "[line 1:1] STRING \"hi\\tthere\" (=hi\tthere)"
+        );
+    }
+
+    #[test]
+    fn displays_only_the_lexeme_of_operators() {
+        assert_eq!(display("  +"), "[line 1:3] PLUS +");
+    }
+
+    #[test]
+    fn quotes_csv_fields_containing_commas_and_quotes() {
+        let tokens = Scanner::new("\"a, \\\"b\\\"\"".to_string())
+            .scan_tokens()
+            .unwrap();
+
+        assert_eq!(
+            tokens[0].to_csv(),
+            r#"1,1,STRING,"""a, \""b\""""","a, ""b""",0,10"#
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use crate::interp::callable::Callable;
+use crate::interp::class::{Class, Instance};
+use crate::scan::token::Literal;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+// Runtime values. Equality follows Lox: values of different types are
+// never equal and there is no implicit conversion.
+#[derive(Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Callable(Rc<dyn Callable>),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+    Range(Range),
+}
+
+// The integers from `start` up to `end`, produced one at a time while a
+// for-in loop runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: i64,
+    pub end: i64,
+    pub inclusive: bool, // `..=` also yields `end`
+}
+
+impl Range {
+    pub fn iter(&self) -> impl Iterator<Item = i64> {
+        let last = (self.inclusive && self.start <= self.end).then_some(self.end);
+
+        (self.start..self.end).chain(last)
+    }
+
+    // the number of integers `iter` yields
+    pub fn length(&self) -> i64 {
+        let end = match self.inclusive {
+            true => self.end.saturating_add(1),
+            false => self.end,
+        };
+
+        end.saturating_sub(self.start).max(0)
+    }
+
+    pub fn contains(&self, n: f64) -> bool {
+        let below_end = match self.inclusive {
+            true => n <= self.end as f64,
+            false => n < self.end as f64,
+        };
+
+        n.fract() == 0.0 && n >= self.start as f64 && below_end
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            // callables, classes and instances are only equal to themselves
+            (Value::Callable(l), Value::Callable(r)) => Rc::ptr_eq(l, r),
+            (Value::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
+            (Value::Instance(l), Value::Instance(r)) => Rc::ptr_eq(l, r),
+            (Value::Range(l), Value::Range(r)) => l == r,
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    // nil and false are falsey, everything else is truthy
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+}
+
+impl From<&Literal> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Number(n) => Value::Number(*n),
+            Literal::String(s) => Value::String(s.to_string()),
+            Literal::Bool(b) => Value::Bool(*b),
+            Literal::Nil | Literal::None => Value::Nil,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n), // whole numbers print without ".0"
+            Value::String(s) => write!(f, "{}", s),
+            Value::Callable(c) => write!(f, "{}", c),
+            Value::Class(c) => write!(f, "{}", c),
+            Value::Instance(i) => write!(f, "{}", i.borrow()),
+            Value::Range(r) if r.inclusive => write!(f, "{}..={}", r.start, r.end),
+            Value::Range(r) => write!(f, "{}..{}", r.start, r.end),
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+// errors are printed without a backtrace, whatever the environment asks for
+fn rlox(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .env_remove("RUST_BACKTRACE")
+        .env_remove("RUST_LIB_BACKTRACE")
+        .output()
+        .unwrap()
+}
+
+// a fresh directory under the system temp dir, unique to the test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rlox-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+// runs `source` as a script file, passing `args` before its path
+fn run_script(name: &str, source: &str, args: &[&str]) -> Output {
+    let dir = temp_dir(name);
+    let script = dir.join("script.lox");
+    fs::write(&script, source).unwrap();
+
+    let output = rlox(&[args, &[script.to_str().unwrap()]].concat());
+    fs::remove_dir_all(dir).unwrap();
+
+    output
+}
+
+#[test]
+fn syntax_only_reports_every_file_and_fails_on_errors() {
+    let dir = temp_dir("syntax-only");
+    fs::write(dir.join("good.lox"), "print 1 + 2;\n").unwrap();
+    fs::write(dir.join("bad.lox"), "print (1 + ;\n").unwrap();
+
+    let output = rlox(&["--syntax-only", dir.to_str().unwrap()]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stdout.contains(&format!("{}: [line 1]", dir.join("bad.lox").display())));
+    assert!(!stdout.contains("good.lox"));
+    assert!(stdout.ends_with("2 files checked, 1 with errors\n"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn time_reports_each_phase_on_stderr() {
+    let dir = temp_dir("time");
+    let script = dir.join("script.lox");
+    fs::write(&script, "print 1 + 2;\n").unwrap();
+
+    let output = rlox(&["--time", script.to_str().unwrap()]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let phases: Vec<&str> = stderr
+        .lines()
+        .map(|line| line.split(": ").next().unwrap())
+        .collect();
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"3\n");
+    assert_eq!(
+        phases,
+        ["scan", "parse", "resolve", "optimize", "interpret"]
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn trace_logs_each_statement_on_stderr() {
+    let output = run_script("trace", "var a = 1;\nprint a;\n", &["--trace"]);
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"1\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "> line 1: var a = 1;\n> line 2: print a;\n"
+    );
+}
+
+#[test]
+fn trace_quotes_the_first_line_of_nested_statements() {
+    let source = "fun f(x) {\n  return x;\n}\nprint f(2);\n";
+    let output = run_script("trace-nested", source, &["--trace"]);
+
+    assert_eq!(output.stdout, b"2\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "> line 1: fun f(x) {\n> line 4: print f(2);\n> line 2: return x;\n"
+    );
+}
+
+#[test]
+fn lint_levels_are_configurable() {
+    let source = "var a = 1;\nif (a = 2) print a;\n";
+    let warning = "Assignment used as a condition, wrap it in parentheses if it's intended. [assign-in-condition]";
+
+    let warned = run_script("lint-warn", source, &[]);
+    assert!(warned.status.success());
+    assert_eq!(warned.stdout, b"2\n");
+    assert_eq!(
+        String::from_utf8(warned.stderr).unwrap(),
+        format!("[line 2] Warning: {}\n", warning)
+    );
+
+    let allowed = run_script("lint-allow", source, &["--allow", "assign-in-condition"]);
+    assert_eq!(allowed.stdout, b"2\n");
+    assert!(allowed.stderr.is_empty());
+
+    let denied = run_script("lint-deny", source, &["--deny", "assign-in-condition"]);
+    assert!(!denied.status.success());
+    assert!(denied.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(denied.stderr).unwrap(),
+        format!("Error: [line 2] Error: {}\n", warning)
+    );
+}
+
+#[test]
+fn uncaught_throw_is_a_runtime_error() {
+    let output = run_script(
+        "uncaught-throw",
+        "print 1;\nthrow \"oops\";\nprint 2;\n",
+        &[],
+    );
+
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(output.stdout, b"1\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Error: [line 2] Uncaught exception: oops\n"
+    );
+}
+
+#[test]
+fn exit_ends_the_script_with_its_code_after_finally() {
+    let source = "try { exit(3); } finally { print \"cleanup\"; }\nprint \"after\";\n";
+    let output = run_script("exit", source, &[]);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(output.stdout, b"cleanup\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn panic_ends_the_script_with_70() {
+    let source =
+        "try { panic(\"boom\"); } catch (e) { print \"caught\"; } finally { print \"cleanup\"; }\n";
+    let output = run_script("panic", source, &["--catch-runtime-errors"]);
+
+    assert_eq!(output.status.code(), Some(70));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Error: [line 1] Panic: boom\n"
+    );
+}
+
+#[test]
+fn exit_ends_the_repl() {
+    let output = repl_with("repl-exit", "", "print 1;\nexit(4);\nprint 2;\n");
+
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(output.stdout, b"Starting REPL\n1\n");
+}
+
+#[test]
+fn syntax_errors_are_all_reported_and_nothing_runs() {
+    let output = run_script(
+        "syntax-errors",
+        "print 1;\nvar = 2;\nprint 3;\nprint (4;\n",
+        &[],
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        stderr.lines().collect::<Vec<_>>(),
+        [
+            "Error: [line 2] Error at '=': Expect variable name.",
+            "[line 4] Error at ';': Expect ')' after expression."
+        ]
+    );
+}
+
+#[test]
+fn max_errors_caps_the_reported_errors() {
+    let source = "@\n".repeat(30);
+
+    let capped = run_script("max-errors", &source, &["--max-errors", "5"]);
+    let stderr = String::from_utf8(capped.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+
+    assert!(!capped.status.success());
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[4], "[line 5] Unexpected character '@'");
+    assert_eq!(lines[5], "... and 25 more errors");
+
+    let default = run_script("max-errors-default", &source, &[]);
+    let stderr = String::from_utf8(default.stderr).unwrap();
+
+    assert_eq!(stderr.lines().count(), 21);
+    assert!(stderr.ends_with("... and 10 more errors\n"));
+
+    let parse_errors = run_script(
+        "max-errors-parse",
+        &"print;\n".repeat(4),
+        &["--max-errors", "3"],
+    );
+    let stderr = String::from_utf8(parse_errors.stderr).unwrap();
+
+    assert_eq!(stderr.lines().count(), 4);
+    assert!(stderr.ends_with("... and 1 more error\n"));
+}
+
+// starts the REPL, preloading `lib`, and types `input` at the prompt
+fn repl_with(name: &str, lib: &str, input: &str) -> Output {
+    let dir = temp_dir(name);
+    let lib_path = dir.join("lib.lox");
+    fs::write(&lib_path, lib).unwrap();
+
+    let mut repl = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(["--repl-load", lib_path.to_str().unwrap()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    repl.stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    let output = repl.wait_with_output().unwrap();
+    fs::remove_dir_all(dir).unwrap();
+
+    output
+}
+
+#[test]
+fn repl_load_keeps_the_file_definitions() {
+    let output = repl_with("repl-load", "fun f() { return 42; }\n", "f();\n");
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"Starting REPL\n42\n");
+}
+
+#[test]
+fn repl_load_errors_still_start_the_repl() {
+    let output = repl_with(
+        "repl-load-error",
+        "fun f() { return 42; }\nprint g();\n",
+        "f();\n",
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(stderr, "Error: [line 2] Undefined variable 'g'.\n");
+    assert_eq!(output.stdout, b"Starting REPL\n42\n");
+}
+
+#[test]
+fn prints_true() {
+    assert_eq!(
+        run_script("print-true", "print true;", &[]).stdout,
+        b"true\n"
+    );
+}
+
+#[test]
+fn prints_false() {
+    assert_eq!(
+        run_script("print-false", "print false;", &[]).stdout,
+        b"false\n"
+    );
+}
+
+#[test]
+fn prints_nil() {
+    assert_eq!(run_script("print-nil", "print nil;", &[]).stdout, b"nil\n");
+}